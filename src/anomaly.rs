@@ -0,0 +1,174 @@
+//! Outlier and change-point detection over a series' values.
+
+use stats;
+
+use std::str::FromStr;
+
+#[derive(Clone, Copy)]
+pub enum Method {
+    /// Distance from the mean in standard deviations
+    ZScore,
+    /// Modified z-score using the median absolute deviation, robust to the
+    /// outliers themselves skewing the baseline
+    Mad,
+    /// Two-sided cumulative sum change-point detection
+    Cusum
+}
+
+impl Method {
+    /// Sensible default threshold for each method.
+    pub fn default_threshold(&self) -> f64 {
+        match *self {
+            Method::ZScore => 3.0,
+            Method::Mad => 3.5,
+            Method::Cusum => 5.0
+        }
+    }
+}
+
+impl FromStr for Method {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "zscore" => Ok(Method::ZScore),
+            "mad" => Ok(Method::Mad),
+            "cusum" => Ok(Method::Cusum),
+            m => Err(format!("unknown method '{}', expected zscore, mad or cusum", m))
+        }
+    }
+}
+
+pub fn stddev(values: &[f64]) -> Option<f64> {
+    let mean = stats::mean(values)?;
+    let var = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / values.len() as f64;
+    Some(var.sqrt())
+}
+
+/// Indexes of values more than `threshold` standard deviations from the mean.
+pub fn zscore(values: &[f64], threshold: f64) -> Vec<usize> {
+    let (mean, sd) = match (stats::mean(values), stddev(values)) {
+        (Some(m), Some(sd)) if sd > 0.0 => (m, sd),
+        _ => return vec![]
+    };
+
+    values.iter()
+        .enumerate()
+        .filter(|(_, v)| ((*v - mean) / sd).abs() > threshold)
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// Indexes of values whose modified z-score (Iglewicz and Hoaglin) exceeds
+/// `threshold`.
+pub fn mad(values: &[f64], threshold: f64) -> Vec<usize> {
+    let median = match stats::median(values) {
+        Some(m) => m,
+        None => return vec![]
+    };
+
+    let deviations = values.iter().map(|v| (v - median).abs()).collect::<Vec<_>>();
+    let mad = match stats::median(&deviations) {
+        Some(m) if m > 0.0 => m,
+        _ => return vec![]
+    };
+
+    values.iter()
+        .enumerate()
+        .filter(|(_, v)| (0.6745 * (*v - median) / mad).abs() > threshold)
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// A detected shift in level.
+pub struct ChangePoint {
+    /// Index of the first value after the change
+    pub index: usize,
+    /// Whether the level went up
+    pub up: bool
+}
+
+/// Two-sided CUSUM. Deviations from the baseline (the mean since the last
+/// change) are accumulated less a slack of half a standard deviation, and a
+/// change is flagged when either sum passes `threshold` standard deviations.
+/// The change is placed where that sum last started growing from zero.
+pub fn cusum(values: &[f64], threshold: f64) -> Vec<ChangePoint> {
+    let sd = match stddev(values) {
+        Some(sd) if sd > 0.0 => sd,
+        _ => return vec![]
+    };
+
+    let slack = 0.5 * sd;
+    let limit = threshold * sd;
+
+    let mut changes = vec![];
+    let mut start = 0;
+    let (mut high, mut low) = (0.0, 0.0);
+    let (mut high_onset, mut low_onset) = (0, 0);
+
+    for i in 1..values.len() {
+        let baseline = stats::mean(&values[start..i]).unwrap_or(values[i]);
+
+        if high == 0.0 { high_onset = i; }
+        if low == 0.0 { low_onset = i; }
+
+        high = f64::max(0.0, high + values[i] - baseline - slack);
+        low = f64::max(0.0, low + baseline - values[i] - slack);
+
+        if high > limit || low > limit {
+            let up = high > limit;
+            let index = if up { high_onset } else { low_onset };
+
+            changes.push(ChangePoint { index, up });
+            start = index;
+            high = 0.0;
+            low = 0.0;
+        }
+    }
+
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spiky() -> Vec<f64> {
+        let mut values = vec![10.0, 11.0, 9.0, 10.0, 12.0, 10.0, 9.0, 11.0, 10.0, 10.0];
+        values[6] = 50.0;
+        values
+    }
+
+    #[test]
+    fn zscore_finds_the_spike() {
+        assert_eq!(vec![6], zscore(&spiky(), 2.5));
+        assert!(zscore(&[5.0; 4], 1.0).is_empty());
+        assert!(zscore(&[], 1.0).is_empty());
+    }
+
+    #[test]
+    fn mad_finds_the_spike() {
+        assert_eq!(vec![6], mad(&spiky(), Method::Mad.default_threshold()));
+        assert!(mad(&[5.0; 4], 1.0).is_empty());
+    }
+
+    #[test]
+    fn cusum_places_the_step() {
+        let mut values = vec![10.0; 20];
+        values.extend(vec![20.0; 20]);
+        let changes = cusum(&values, Method::Cusum.default_threshold());
+        assert_eq!(1, changes.len());
+        assert_eq!(20, changes[0].index);
+        assert!(changes[0].up);
+
+        let down = values.iter().rev().cloned().collect::<Vec<_>>();
+        let changes = cusum(&down, Method::Cusum.default_threshold());
+        assert_eq!(20, changes[0].index);
+        assert!(!changes[0].up);
+    }
+
+    #[test]
+    fn cusum_ignores_flat_series() {
+        assert!(cusum(&[3.0; 10], 5.0).is_empty());
+    }
+}
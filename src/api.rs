@@ -0,0 +1,283 @@
+//! JSON HTTP API over a `DataSource`.
+//!
+//! - `GET /series` lists series
+//! - `POST /series` creates one from `{"name": .., "unit": ..}`
+//! - `GET /series/<name>?points=N` or `?from=TS&to=TS` returns measurements
+//! - `POST /series/<name>` records `{"value": .., "timestamp": .., "tags": {..}}`
+//! - `DELETE /series/<name>` deletes a series and its measurements
+//! - `POST /measurements` records a batch of `{"series": .., "value": ..}`
+//! - `POST /write` records InfluxDB line protocol
+//! - `GET /metrics` returns the latest values for Prometheus
+//!
+//! Recording endpoints take `?create=true` to auto-create series.
+
+use http::{Request, Response};
+use data::{DataSource, Series};
+use influx;
+use prometheus;
+
+use rusqlite;
+use serde_json::{self, Value};
+
+use std::collections::BTreeMap;
+
+#[derive(Deserialize)]
+struct NewSeries {
+    name: String,
+    #[serde(default)]
+    unit: String
+}
+
+#[derive(Deserialize)]
+struct NewMeasurement {
+    series: Option<String>,
+    value: f64,
+    timestamp: Option<u32>,
+    #[serde(default)]
+    tags: BTreeMap<String, String>
+}
+
+fn json(status: u16, value: Value) -> Response {
+    Response::json(status, value.to_string())
+}
+
+fn error(status: u16, msg: &str) -> Response {
+    json(status, json!({ "error": msg }))
+}
+
+fn db_error(e: rusqlite::Error) -> Response {
+    error!("API data source error: {}", e);
+    error(500, "data source error")
+}
+
+fn series_json(series: &Series) -> Value {
+    json!({ "name": series.name, "unit": series.unit })
+}
+
+fn is_true(value: Option<&str>) -> bool {
+    match value {
+        Some(v) => v.is_empty() || v == "1" || v == "true",
+        None => false
+    }
+}
+
+pub fn handle(db: &DataSource, req: &Request) -> Response {
+    let create = is_true(req.param("create"));
+    let method = req.method.as_str();
+
+    if let Some(name) = req.path.strip_prefix("/series/") {
+        return match method {
+            "GET" => get_series(db, name, req),
+            "POST" => {
+                match serde_json::from_slice::<NewMeasurement>(&req.body) {
+                    Ok(m) => match record(db, name, &m, create) {
+                        Ok(r) => r,
+                        Err(e) => db_error(e)
+                    },
+                    Err(e) => error(400, &format!("invalid measurement: {}", e))
+                }
+            },
+            "DELETE" => delete_series(db, name),
+            _ => error(405, "method not allowed")
+        };
+    }
+
+    match (method, req.path.as_str()) {
+        ("GET", "/series") => match db.list_series() {
+            Ok(list) => json(200, Value::Array(list.iter().map(series_json).collect())),
+            Err(e) => db_error(e)
+        },
+        ("POST", "/series") => create_series(db, req),
+        ("POST", "/measurements") => {
+            match serde_json::from_slice::<Vec<NewMeasurement>>(&req.body) {
+                Ok(batch) => record_batch(db, &batch, create),
+                Err(e) => error(400, &format!("invalid measurements: {}", e))
+            }
+        },
+        ("POST", "/write") => write_lines(db, req, create),
+        ("GET", "/metrics") => match prometheus::render(db, &[]) {
+            Ok(body) => Response::new(200, "text/plain; version=0.0.4; charset=utf-8", body),
+            Err(e) => db_error(e)
+        },
+        (_, "/series") | (_, "/measurements") | (_, "/write") | (_, "/metrics") => {
+            error(405, "method not allowed")
+        },
+        _ => error(404, "not found")
+    }
+}
+
+fn get_series(db: &DataSource, name: &str, req: &Request) -> Response {
+    let from = req.param("from").and_then(|v| v.parse::<u32>().ok());
+    let to = req.param("to").and_then(|v| v.parse::<u32>().ok());
+    let points = match req.param("points") {
+        Some(p) => match p.parse::<u8>() {
+            Ok(p) => p,
+            Err(_) => return error(400, "points must be 0-255")
+        },
+        None => 50
+    };
+
+    let result = if from.is_some() || to.is_some() {
+        db.range(name, from.unwrap_or(0), to.unwrap_or(u32::MAX))
+    } else {
+        db.series(name, points)
+    };
+
+    let series = match result {
+        Ok(Some(s)) => s,
+        Ok(None) => return error(404, "series not found"),
+        Err(e) => return db_error(e)
+    };
+
+    let mut measurements = vec![];
+    for m in &series.measurements {
+        let tags = match db.tags(series.id, m.run) {
+            Ok(t) => t.into_iter().collect::<BTreeMap<_, _>>(),
+            Err(e) => return db_error(e)
+        };
+
+        measurements.push(json!({
+            "timestamp": m.timestamp,
+            "run": m.run,
+            "value": m.measurement,
+            "tags": tags
+        }));
+    }
+
+    let mut body = series_json(&series);
+    body["measurements"] = Value::Array(measurements);
+
+    json(200, body)
+}
+
+fn create_series(db: &DataSource, req: &Request) -> Response {
+    let new = match serde_json::from_slice::<NewSeries>(&req.body) {
+        Ok(s) => s,
+        Err(e) => return error(400, &format!("invalid series: {}", e))
+    };
+
+    match db.get_series(&new.name) {
+        Ok(Some(_)) => return error(409, "series already exists"),
+        Ok(None) => {},
+        Err(e) => return db_error(e)
+    }
+
+    match db.create_series(&new.name, &new.unit) {
+        Ok(series) => json(201, series_json(&series)),
+        Err(e) => db_error(e)
+    }
+}
+
+fn delete_series(db: &DataSource, name: &str) -> Response {
+    match db.get_series(name) {
+        Ok(Some(_)) => {},
+        Ok(None) => return error(404, "series not found"),
+        Err(e) => return db_error(e)
+    }
+
+    match db.delete_series(name) {
+        Ok(n) => json(200, json!({ "name": name, "deleted": n })),
+        Err(e) => db_error(e)
+    }
+}
+
+/// Records one measurement, refusing virtual series and unknown series
+/// unless `create` is set (`DataSource::measure_at` would otherwise exit the
+/// process).
+fn record(db: &DataSource, name: &str, m: &NewMeasurement, create: bool) -> rusqlite::Result<Response> {
+    match db.get_series(name)? {
+        Some(ref s) if s.expression.is_some() => {
+            return Ok(error(400, &format!("{} is a virtual series", name)));
+        },
+        None if !create => {
+            return Ok(error(404, &format!("series {} not found, use ?create=true", name)));
+        },
+        _ => {}
+    }
+
+    let tags = m.tags.iter()
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect::<Vec<_>>();
+    db.measure_at(name, m.value, m.timestamp, &tags, create)?;
+
+    Ok(json(201, json!({ "series": name, "value": m.value })))
+}
+
+fn record_batch(db: &DataSource, batch: &[NewMeasurement], create: bool) -> Response {
+    // Check everything up front so a bad entry is reported before anything is
+    // written, then write the batch in one transaction
+    for m in batch {
+        let name = match m.series {
+            Some(ref s) => s,
+            None => return error(400, "measurement is missing series")
+        };
+
+        match db.get_series(name) {
+            Ok(Some(ref s)) if s.expression.is_some() => {
+                return error(400, &format!("{} is a virtual series", name));
+            },
+            Ok(None) if !create => {
+                return error(404, &format!("series {} not found, use ?create=true", name));
+            },
+            Ok(_) => {},
+            Err(e) => return db_error(e)
+        }
+    }
+
+    let written = db.transaction(|| {
+        for m in batch {
+            let name = m.series.as_ref().unwrap();
+            record(db, name, m, create)?;
+        }
+        Ok(())
+    });
+
+    if let Err(e) = written {
+        return db_error(e);
+    }
+
+    json(201, json!({ "recorded": batch.len() }))
+}
+
+fn write_lines(db: &DataSource, req: &Request, create: bool) -> Response {
+    let body = String::from_utf8_lossy(&req.body);
+
+    let mut lines = vec![];
+    for line in body.lines().map(|l| l.trim()).filter(|l| !l.is_empty() && !l.starts_with('#')) {
+        match influx::parse(line) {
+            Ok(l) => lines.push(l),
+            Err(e) => return error(400, &format!("invalid line '{}': {}", line, e))
+        }
+    }
+
+    let points = lines.iter()
+        .flat_map(|l| l.series().into_iter().map(move |(series, value)| (series, value, l)))
+        .collect::<Vec<_>>();
+
+    for (series, _, _) in &points {
+        match db.get_series(series) {
+            Ok(Some(ref s)) if s.expression.is_some() => {
+                return error(400, &format!("{} is a virtual series", series));
+            },
+            Ok(None) if !create => {
+                return error(404, &format!("series {} not found, use ?create=true", series));
+            },
+            Ok(_) => {},
+            Err(e) => return db_error(e)
+        }
+    }
+
+    let written = db.transaction(|| {
+        for (series, value, line) in &points {
+            db.measure_at(series, *value, line.timestamp, &line.tags, create)?;
+        }
+        Ok(())
+    });
+
+    if let Err(e) = written {
+        return db_error(e);
+    }
+
+    debug!("Recorded {} points via /write", points.len());
+    Response::new(204, "text/plain; charset=utf-8", Vec::new())
+}
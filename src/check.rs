@@ -0,0 +1,173 @@
+//! Nagios-style threshold checks.
+
+use std::fmt;
+use std::str::FromStr;
+
+/// A comparison against a fixed value, eg `>100` or `<=0.5`.
+#[derive(Clone, Copy)]
+pub struct Threshold {
+    op: Op,
+    value: f64
+}
+
+#[derive(Clone, Copy)]
+enum Op { Gt, Ge, Lt, Le, Eq, Ne }
+
+impl Threshold {
+    /// Whether `value` breaches this threshold.
+    pub fn breached(&self, value: f64) -> bool {
+        match self.op {
+            Op::Gt => value > self.value,
+            Op::Ge => value >= self.value,
+            Op::Lt => value < self.value,
+            Op::Le => value <= self.value,
+            Op::Eq => (value - self.value).abs() < f64::EPSILON,
+            Op::Ne => (value - self.value).abs() >= f64::EPSILON
+        }
+    }
+
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+
+    /// Whether values above (`Some(true)`) or below (`Some(false)`) the
+    /// value breach this threshold, `None` for `==` and `!=`.
+    pub fn above(&self) -> Option<bool> {
+        match self.op {
+            Op::Gt | Op::Ge => Some(true),
+            Op::Lt | Op::Le => Some(false),
+            Op::Eq | Op::Ne => None
+        }
+    }
+}
+
+impl FromStr for Threshold {
+    type Err = String;
+
+    /// Parses `>N`, `>=N`, `<N`, `<=N`, `==N` or `!=N`. A bare number means `>N`.
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let input = input.trim();
+        let (op, rest) = if let Some(r) = input.strip_prefix(">=") {
+            (Op::Ge, r)
+        } else if let Some(r) = input.strip_prefix("<=") {
+            (Op::Le, r)
+        } else if let Some(r) = input.strip_prefix("==") {
+            (Op::Eq, r)
+        } else if let Some(r) = input.strip_prefix("!=") {
+            (Op::Ne, r)
+        } else if let Some(r) = input.strip_prefix('>') {
+            (Op::Gt, r)
+        } else if let Some(r) = input.strip_prefix('<') {
+            (Op::Lt, r)
+        } else {
+            (Op::Gt, input)
+        };
+
+        let value = rest.trim().parse::<f64>()
+            .map_err(|e| format!("invalid threshold '{}': {}", input, e))?;
+
+        Ok(Threshold { op, value })
+    }
+}
+
+impl fmt::Display for Threshold {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let op = match self.op {
+            Op::Gt => ">",
+            Op::Ge => ">=",
+            Op::Lt => "<",
+            Op::Le => "<=",
+            Op::Eq => "==",
+            Op::Ne => "!="
+        };
+        write!(f, "{}{}", op, self.value)
+    }
+}
+
+/// Check result, doubling as the process exit code.
+#[derive(Clone, Copy, PartialEq, PartialOrd)]
+pub enum Status {
+    Ok = 0,
+    Warning = 1,
+    Critical = 2,
+    Unknown = 3
+}
+
+impl Status {
+    pub fn evaluate(value: f64, warn: Option<Threshold>, crit: Option<Threshold>) -> Self {
+        if crit.map(|t| t.breached(value)).unwrap_or(false) {
+            Status::Critical
+        } else if warn.map(|t| t.breached(value)).unwrap_or(false) {
+            Status::Warning
+        } else {
+            Status::Ok
+        }
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            Status::Ok => "OK",
+            Status::Warning => "WARNING",
+            Status::Critical => "CRITICAL",
+            Status::Unknown => "UNKNOWN"
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// `unit` as a Nagios perfdata unit of measurement, if it is one.
+pub fn uom(unit: &str) -> Option<&'static str> {
+    ["s", "ms", "us", "%", "B", "KB", "MB", "GB", "TB", "c"].iter()
+        .find(|u| **u == unit)
+        .cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn t(s: &str) -> Threshold {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_operators() {
+        assert_eq!(">=1.5", t(">= 1.5").to_string());
+        assert_eq!("<0", t("<0").to_string());
+        assert_eq!("!=3", t("!=3").to_string());
+        assert_eq!(">100", t("100").to_string());
+        assert!("> lots".parse::<Threshold>().is_err());
+    }
+
+    #[test]
+    fn breaches() {
+        assert!(t(">100").breached(101.0));
+        assert!(!t(">100").breached(100.0));
+        assert!(t(">=100").breached(100.0));
+        assert!(t("<=0").breached(0.0));
+        assert!(t("==2").breached(2.0));
+        assert!(t("!=2").breached(3.0));
+        assert_eq!(Some(false), t("<5").above());
+        assert_eq!(None, t("==5").above());
+    }
+
+    #[test]
+    fn critical_wins_over_warning() {
+        let (warn, crit) = (Some(t(">10")), Some(t(">20")));
+        assert!(Status::evaluate(5.0, warn, crit) == Status::Ok);
+        assert!(Status::evaluate(15.0, warn, crit) == Status::Warning);
+        assert!(Status::evaluate(25.0, warn, crit) == Status::Critical);
+        assert!(Status::evaluate(25.0, None, None) == Status::Ok);
+    }
+
+    #[test]
+    fn perfdata_units() {
+        assert_eq!(Some("ms"), uom("ms"));
+        assert_eq!(Some("%"), uom("%"));
+        assert_eq!(Some("KB"), uom("KB"));
+        assert_eq!(None, uom("req/s"));
+        assert_eq!(None, uom(""));
+    }
+}
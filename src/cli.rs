@@ -5,10 +5,107 @@ use std::num::ParseFloatError;
 
 use std::str::FromStr;
 
+use log::LevelFilter;
+
+use anomaly::Method;
+use check::Threshold;
+use duration;
+use meta::Kind;
+use stats::Aggregation;
+use transform::Transform;
+use trend::Model;
+
 fn parse_measurement(input: &str) -> Result<f64, ParseFloatError> {
     f64::from_str(input)
 }
 
+fn parse_duration(input: &str) -> Result<u32, String> {
+    duration::parse(input)
+}
+
+/// Input format for `bulk`
+pub enum BulkFormat {
+    /// `series=value` pairs
+    KeyValue,
+    /// InfluxDB line protocol
+    Influx
+}
+
+impl FromStr for BulkFormat {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "kv" => Ok(BulkFormat::KeyValue),
+            "influx" => Ok(BulkFormat::Influx),
+            f => Err(format!("unknown format '{}', expected kv or influx", f))
+        }
+    }
+}
+
+/// Output format for `export`
+#[derive(Clone, Copy)]
+pub enum ExportFormat {
+    /// Prometheus text exposition format
+    Prometheus
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "prometheus" => Ok(ExportFormat::Prometheus),
+            f => Err(format!("unknown format '{}', expected prometheus", f))
+        }
+    }
+}
+
+/// When to print charts in colour
+#[derive(Clone, Copy)]
+pub enum ColorMode {
+    /// When writing to a terminal and `NO_COLOR` isn't set
+    Auto,
+    Always,
+    Never
+}
+
+impl FromStr for ColorMode {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "auto" => Ok(ColorMode::Auto),
+            "always" => Ok(ColorMode::Always),
+            "never" => Ok(ColorMode::Never),
+            c => Err(format!("unknown colour mode '{}', expected auto, always or never", c))
+        }
+    }
+}
+
+/// How `plot` draws the points
+#[derive(Clone, Copy)]
+pub enum PlotStyle {
+    Line,
+    Step,
+    Bar,
+    Points
+}
+
+impl FromStr for PlotStyle {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "line" => Ok(PlotStyle::Line),
+            "step" => Ok(PlotStyle::Step),
+            "bar" => Ok(PlotStyle::Bar),
+            "points" => Ok(PlotStyle::Points),
+            s => Err(format!("unknown style '{}', expected line, step, bar or points", s))
+        }
+    }
+}
+
 #[derive(StructOpt)]
 pub enum Command {
     /// Add a new measurement to the given series
@@ -22,12 +119,53 @@ pub enum Command {
 
         /// Auto-create the series if it doesn't exist
         #[structopt(short = "c")]
-        create: bool
+        create: bool,
+
+        /// Check the new value against --warn/--crit (or the series' thresholds) and exit like `check`
+        #[structopt(long = "check")]
+        check: bool,
+
+        /// Warning threshold for --check (eg '>100')
+        #[structopt(long = "warn", requires = "check")]
+        warn: Option<Threshold>,
+
+        /// Critical threshold for --check (eg '>200')
+        #[structopt(long = "crit", requires = "check")]
+        crit: Option<Threshold>
+    },
+
+    /// Check a series against thresholds, with Nagios-style output and exit codes
+    #[structopt(name = "check")]
+    Check {
+        /// Series name, or a pattern like 'inv.*' to use every match
+        #[structopt(short = "s", long = "series")]
+        series: String,
+
+        /// Warning threshold (eg '>100', '<=5')
+        #[structopt(long = "warn")]
+        warn: Option<Threshold>,
+
+        /// Critical threshold (eg '>200')
+        #[structopt(long = "crit")]
+        crit: Option<Threshold>,
+
+        /// Aggregate over this window (eg 5m, 1h) instead of using the latest value
+        #[structopt(long = "over", parse(try_from_str = "parse_duration"))]
+        over: Option<u32>,
+
+        /// Aggregation for --over (avg, min, max, sum, count, median, last),
+        /// defaults to the series' or avg
+        #[structopt(long = "agg")]
+        agg: Option<Aggregation>
     },
 
     /// Slurp in series:val pairs from stdin
     #[structopt(name = "bulk")]
     AddBulk {
+        /// Input format (kv, influx)
+        #[structopt(long = "format", default_value = "kv")]
+        format: BulkFormat,
+
         /// Auto-create series if they doesn't exist
         #[structopt(short = "c")]
         create: bool
@@ -41,47 +179,401 @@ pub enum Command {
 
         /// Unit for values (eg ms, bps, pps)
         #[structopt(short = "u", long = "unit")]
-        unit: Option<String>
+        unit: Option<String>,
+
+        /// Make this a virtual series computed from others (eg 'errors / requests * 100')
+        #[structopt(short = "e", long = "expression")]
+        expression: Option<String>,
+
+        /// Time bucket to align the expression's inputs to
+        #[structopt(long = "bucket", default_value = "1m", parse(try_from_str = "parse_duration"))]
+        bucket: u32
+    },
+
+    /// Describe a series and how to show and check it
+    #[structopt(name = "series")]
+    Series {
+        #[structopt(subcommand)]
+        cmd: SeriesCommand
     },
 
     /// Delete a series
     #[structopt(name = "delete-series")]
     DeleteSeries {
+        /// Series name, or a pattern like 'inv.*' to use every match
         #[structopt(short = "s", long = "series")]
-        series: Option<String>
+        series: Option<String>,
+
+        /// Delete every series a pattern matches without asking
+        #[structopt(short = "y", long = "yes")]
+        yes: bool
     },
 
     /// Plot a series
     #[structopt(name = "plot")]
     Plot {
+        /// Series name, or a pattern like 'inv.*' to use every match. Start
+        /// with a data file to read from another one (eg app1:metric.a), give
+        /// several to draw them together
         #[structopt(short = "s", long = "series")]
-        series: Option<String>,
+        series: Vec<String>,
+
+        /// Plot an expression over other series instead (eg 'errors / requests * 100')
+        #[structopt(short = "e", long = "expression")]
+        expression: Option<String>,
+
+        /// Time bucket to align the expression's inputs to
+        #[structopt(long = "bucket", default_value = "1m", parse(try_from_str = "parse_duration"))]
+        bucket: u32,
 
         /// Number of points to plot (default 50)
-        #[structopt(short = "p", default_value = "50")]
-        points: u8,
+        #[structopt(short = "p")]
+        points: Option<u8>,
 
         /// Show a table as well
         #[structopt(short = "t", long = "table")]
         table: bool,
+
+        /// Transform the data first, may be repeated
+        /// (delta, rate, cumsum, moving-avg:N, ewma:alpha, log)
+        #[structopt(long = "transform")]
+        transforms: Vec<Transform>,
+
+        /// How to draw the points (line, step, bar, points), default line
+        #[structopt(long = "style")]
+        style: Option<PlotStyle>,
+
+        /// Overlay a fitted trend (linear, holt or holt:alpha,beta)
+        #[structopt(long = "trend")]
+        trend: Option<Model>,
+
+        /// Estimate when the trend reaches this value
+        #[structopt(long = "target", requires = "trend")]
+        target: Option<f64>,
+
+        /// Number of points to project the trend forward, default 10
+        #[structopt(long = "forecast")]
+        forecast: Option<usize>,
+
+        /// Draw gridlines at the Y-axis ticks
+        #[structopt(long = "grid")]
+        grid: bool,
+
+        /// Leave out gridlines even if the config file turns them on
+        #[structopt(long = "no-grid", conflicts_with = "grid")]
+        no_grid: bool,
+
+        /// Mark a warning threshold (eg '>80'), shaded yellow in colour
+        #[structopt(long = "warn")]
+        warn: Option<Threshold>,
+
+        /// Mark a critical threshold (eg '>95'), shaded red in colour
+        #[structopt(long = "crit")]
+        crit: Option<Threshold>,
+
+        /// Draw a horizontal reference line at this value, may be repeated
+        #[structopt(long = "hline")]
+        hlines: Vec<f32>,
+
+        /// Fix the bottom of the Y-axis
+        #[structopt(long = "ymin")]
+        ymin: Option<f32>,
+
+        /// Fix the top of the Y-axis
+        #[structopt(long = "ymax")]
+        ymax: Option<f32>,
+
+        /// Fit the Y-axis around the data instead of always including zero
+        #[structopt(long = "no-zero")]
+        no_zero: bool,
+
+        /// Use a logarithmic Y-axis (values of zero or less are left out)
+        #[structopt(long = "log")]
+        log: bool,
+
+        /// Chart width in braille dots (2 per column), defaults to fit the terminal
+        #[structopt(long = "width")]
+        width: Option<u32>,
+
+        /// Chart height in braille dots (4 per row), defaults to fit the terminal
+        #[structopt(long = "height")]
+        height: Option<u32>,
+
+        /// Save the chart as an image instead, SVG or PNG by the file extension
+        #[structopt(short = "o", long = "out", parse(from_os_str))]
+        out: Option<PathBuf>,
+    },
+
+    /// Find outliers and changes in level in a series
+    #[structopt(name = "anomalies")]
+    Anomalies {
+        /// Series name, or a pattern like 'inv.*' to use every match
+        #[structopt(short = "s", long = "series")]
+        series: String,
+
+        /// Number of points to look at (default 100)
+        #[structopt(short = "p", default_value = "100")]
+        points: u8,
+
+        /// Detection method (zscore, mad, cusum)
+        #[structopt(short = "m", long = "method", default_value = "mad")]
+        method: Method,
+
+        /// Sensitivity, defaults to 3 (zscore), 3.5 (mad) or 5 (cusum)
+        #[structopt(long = "threshold")]
+        threshold: Option<f64>,
+
+        /// Plot the series with anomalies marked
+        #[structopt(long = "plot")]
+        plot: bool
+    },
+
+    /// List the series with their number of points
+    #[structopt(name = "list")]
+    List {
+        /// Every data file under the data root, not just the one selected
+        #[structopt(long = "all-files")]
+        all_files: bool
+    },
+
+    /// Show series names as a tree split on dots, with the number of series
+    /// under each branch
+    #[structopt(name = "tree")]
+    Tree {
+        /// Only the branch below this prefix (eg 'inv.req')
+        prefix: Option<String>,
+
+        /// Levels to show, deeper branches are collapsed to their counts
+        #[structopt(long = "depth")]
+        depth: Option<usize>
+    },
+
+    /// Show a one-line sparkline of each series
+    #[structopt(name = "spark")]
+    Spark {
+        /// Only series matching this pattern, * matches anything (eg 'inv.*')
+        pattern: Option<String>,
+
+        /// Number of points in each sparkline (default 30)
+        #[structopt(short = "p", default_value = "30")]
+        points: u8
+    },
+
+    /// Show a dashboard of charts from the config file
+    #[structopt(name = "dash")]
+    Dash {
+        /// Dashboard name, from the [dashboards] section of ~/.config/trk/config.toml
+        name: String
+    },
+
+    /// Write an HTML report with a chart and stats for each series
+    #[structopt(name = "report")]
+    Report {
+        /// Only series matching any of these patterns, * matches anything (eg 'inv.*')
+        patterns: Vec<String>,
+
+        /// Number of points per series (default 255)
+        #[structopt(short = "p", default_value = "255")]
+        points: u8,
+
+        /// Write to a file instead of stdout
+        #[structopt(short = "o", long = "out", parse(from_os_str))]
+        out: Option<PathBuf>
+    },
+
+    /// Show the distribution of a series' values
+    #[structopt(name = "histogram")]
+    Histogram {
+        /// Series name, or a pattern like 'inv.*' to use every match
+        #[structopt(short = "s", long = "series")]
+        series: String,
+
+        /// Number of points to include (default 255)
+        #[structopt(short = "p", default_value = "255")]
+        points: u8,
+
+        /// Number of bins
+        #[structopt(long = "bins", default_value = "10")]
+        bins: usize
+    },
+
+    /// Compare the latest window of a series against an earlier one
+    #[structopt(name = "compare")]
+    Compare {
+        /// Series name, or a pattern like 'inv.*' to use every match
+        #[structopt(short = "s", long = "series")]
+        series: String,
+
+        /// Length of each window (eg 1d, 7d)
+        #[structopt(long = "window", default_value = "7d", parse(try_from_str = "parse_duration"))]
+        window: u32,
+
+        /// How far back the earlier window is, defaults to --window
+        #[structopt(long = "offset", parse(try_from_str = "parse_duration"))]
+        offset: Option<u32>
+    },
+
+    /// Listen for StatsD metrics and record them
+    #[structopt(name = "statsd")]
+    Statsd {
+        /// Address to listen on
+        #[structopt(long = "listen", default_value = "127.0.0.1:8125")]
+        listen: String,
+
+        /// Flush interval in seconds
+        #[structopt(long = "flush", default_value = "10")]
+        flush: u64,
+
+        /// Auto-create series if they doesn't exist
+        #[structopt(short = "c")]
+        create: bool
+    },
+
+    /// Serve series over HTTP
+    #[structopt(name = "serve")]
+    Serve {
+        /// Address to serve the JSON API on
+        #[structopt(long = "listen")]
+        listen: Option<String>,
+
+        /// Address to expose the latest values for Prometheus on
+        #[structopt(long = "prometheus")]
+        prometheus: Option<String>
+    },
+
+    /// Export the latest value of every series
+    #[structopt(name = "export")]
+    Export {
+        /// Output format (prometheus), default prometheus
+        #[structopt(long = "format")]
+        format: Option<ExportFormat>,
+
+        /// Transform each series before taking its latest value, may be repeated
+        #[structopt(long = "transform")]
+        transforms: Vec<Transform>,
+
+        /// Write to a file instead of stdout
+        #[structopt(short = "o", long = "out", parse(from_os_str))]
+        out: Option<PathBuf>
+    },
+
+    /// Record samples from Prometheus/OpenMetrics text
+    #[structopt(name = "scrape-prom")]
+    ScrapeProm {
+        /// File or http:// URL to read, stdin if omitted or -
+        source: Option<String>,
+
+        /// Only record this metric (may be repeated)
+        #[structopt(short = "m", long = "metric")]
+        metrics: Vec<String>,
+
+        /// Only record samples with this label, as key=value (may be repeated)
+        #[structopt(short = "l", long = "label")]
+        labels: Vec<String>,
+
+        /// Auto-create series if they doesn't exist
+        #[structopt(short = "c")]
+        create: bool
+    },
+
+    /// Listen for Graphite plaintext metrics and record them
+    #[structopt(name = "graphite")]
+    Graphite {
+        /// Address to listen on
+        #[structopt(long = "listen", default_value = "127.0.0.1:2003")]
+        listen: String,
+
+        /// Auto-create series if they doesn't exist
+        #[structopt(short = "c")]
+        create: bool
+    }
+}
+
+#[derive(StructOpt)]
+pub enum SeriesCommand {
+    /// Set metadata for a series
+    #[structopt(name = "set")]
+    Set {
+        /// Series name, or a pattern like 'inv.*' to use every match
+        #[structopt(short = "s", long = "series")]
+        series: String,
+
+        /// Name to show in titles instead of the series name
+        #[structopt(long = "display-name")]
+        display_name: Option<String>,
+
+        #[structopt(long = "description")]
+        description: Option<String>,
+
+        /// Decimal places to show values with
+        #[structopt(long = "precision")]
+        precision: Option<usize>,
+
+        /// Lowest value expected, the default bottom of the Y-axis
+        #[structopt(long = "min")]
+        min: Option<f64>,
+
+        /// Highest value expected, the default top of the Y-axis
+        #[structopt(long = "max")]
+        max: Option<f64>,
+
+        /// Warning threshold for check and plots (eg '>100')
+        #[structopt(long = "warn")]
+        warn: Option<Threshold>,
+
+        /// Critical threshold for check and plots (eg '>200')
+        #[structopt(long = "crit")]
+        crit: Option<Threshold>,
+
+        /// Aggregation for check --over (avg, min, max, sum, count, median, last)
+        #[structopt(long = "agg")]
+        agg: Option<Aggregation>,
+
+        /// What the series measures (gauge, counter)
+        #[structopt(long = "kind")]
+        kind: Option<Kind>,
+
+        /// Clear a field (display-name, description, precision, min, max, warn, crit, agg, kind), may be repeated
+        #[structopt(long = "unset")]
+        unset: Vec<String>
+    },
+
+    /// Show the metadata of a series
+    #[structopt(name = "show")]
+    Show {
+        /// Series name, or a pattern like 'inv.*' to use every match
+        #[structopt(short = "s", long = "series")]
+        series: String
     }
 }
 
 #[derive(StructOpt)]
 #[structopt(name = "trk", about = "Simple CLI-based metric tracker and plotter")]
 pub struct Config {
-    /// Path to store data
-    #[structopt(short = "d", long = "data-root", parse(from_os_str))]
+    /// Config file, defaults to ~/.config/trk/config.toml
+    #[structopt(long = "config", env = "TRK_CONFIG", parse(from_os_str))]
+    pub config: Option<PathBuf>,
+
+    /// Path to store data (default ~/.trk)
+    #[structopt(short = "d", long = "data-root", env = "TRK_DATA_ROOT", parse(from_os_str))]
     pub data_root: Option<PathBuf>,
 
-    /// Source name
-    #[structopt(short = "f", long = "data-file", default_value = "default", parse(from_os_str))]
-    pub file: PathBuf,
+    /// Source name (default "default")
+    #[structopt(short = "f", long = "data-file", env = "TRK_DATA_FILE", parse(from_os_str))]
+    pub file: Option<PathBuf>,
 
     /// Log file
-    #[structopt(short = "l", long = "log", parse(from_os_str))]
+    #[structopt(short = "l", long = "log", env = "TRK_LOG_FILE", parse(from_os_str))]
     pub log_file: Option<PathBuf>,
 
+    /// Log level (off, error, warn, info, debug, trace), default warn
+    #[structopt(long = "log-level", env = "TRK_LOG_LEVEL")]
+    pub log_level: Option<LevelFilter>,
+
+    /// Colour charts (auto, always, never), auto honours NO_COLOR
+    #[structopt(long = "color", env = "TRK_COLOR")]
+    pub color: Option<ColorMode>,
+
     #[structopt(subcommand)]
     pub subcmd: Command
 }
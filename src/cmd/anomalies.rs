@@ -0,0 +1,129 @@
+use do_error;
+use cmd::plot::{self, Canvas};
+use anomaly::{self, Method};
+use data::{DataSource, Measurement};
+use stats::{self, Aggregation};
+use textplots::{Color, Shape, Plot};
+
+use prettytable::Table;
+use chrono::{Local, TimeZone};
+
+fn format_ts(m: &Measurement) -> String {
+    Local.timestamp(m.timestamp.into(), 0).format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+/// Groups sorted indexes into runs of consecutive values.
+fn runs(flagged: &[usize]) -> Vec<(usize, usize)> {
+    let mut runs: Vec<(usize, usize)> = vec![];
+
+    for &i in flagged {
+        match runs.last_mut() {
+            Some(run) if run.1 + 1 == i => run.1 = i,
+            _ => runs.push((i, i))
+        }
+    }
+
+    runs
+}
+
+pub fn anomalies(
+    db: &DataSource,
+    series: &str,
+    points: u8,
+    method: Method,
+    threshold: Option<f64>,
+    plot: bool,
+    color: bool
+) -> i32 {
+    let data = match db.series(series, points) {
+        Ok(Some(d)) => d,
+        Ok(None) => {
+            println!("Series not found");
+            return 1;
+        },
+        Err(e) => {
+            do_error("Error querying series data", e);
+            return 1;
+        }
+    };
+
+    // Oldest first
+    let measurements = data.measurements.iter().rev().collect::<Vec<_>>();
+    let values = measurements.iter().map(|m| m.measurement).collect::<Vec<_>>();
+    let threshold = threshold.unwrap_or_else(|| method.default_threshold());
+    let precision = data.meta.precision.unwrap_or(2);
+
+    println!("# Series: {}\n", series);
+
+    let mut table = Table::new();
+    let marks = match method {
+        Method::ZScore | Method::Mad => {
+            let flagged = match method {
+                Method::ZScore => anomaly::zscore(&values, threshold),
+                _ => anomaly::mad(&values, threshold)
+            };
+
+            table.add_row(row!["FROM", "TO", "POINTS", "MIN", "MAX"]);
+            for (from, to) in runs(&flagged) {
+                let run = &values[from..=to];
+                table.add_row(row![
+                    format_ts(measurements[from]),
+                    format_ts(measurements[to]),
+                    run.len(),
+                    format!("{:.*}", precision, stats::aggregate(run, Aggregation::Min).unwrap_or(0.0)),
+                    format!("{:.*}", precision, stats::aggregate(run, Aggregation::Max).unwrap_or(0.0))
+                ]);
+            }
+
+            flagged
+        },
+        Method::Cusum => {
+            let changes = anomaly::cusum(&values, threshold);
+
+            table.add_row(row!["TIMESTAMP", "DIRECTION", "BEFORE", "AFTER"]);
+            for (n, change) in changes.iter().enumerate() {
+                let from = if n == 0 { 0 } else { changes[n - 1].index };
+                let to = changes.get(n + 1).map(|c| c.index).unwrap_or(values.len());
+
+                table.add_row(row![
+                    format_ts(measurements[change.index]),
+                    if change.up { "up" } else { "down" },
+                    format!("{:.*}", precision, stats::mean(&values[from..change.index]).unwrap_or(0.0)),
+                    format!("{:.*}", precision, stats::mean(&values[change.index..to]).unwrap_or(0.0))
+                ]);
+            }
+
+            changes.iter().map(|c| c.index).collect()
+        }
+    };
+
+    if plot && !values.is_empty() {
+        let prepared = values.iter()
+            .enumerate()
+            .map(|(i, v)| (i as f32, *v as f32))
+            .collect::<Vec<_>>();
+        let x_width = (prepared.len() as f32 - 1.0).max(1.0);
+
+        match plot::canvas(None, None) {
+            Canvas::Chart(width, height) => {
+                let mut chart = plot::chart(width, height, 0.0, x_width, &data.unit, color);
+                chart.lineplot( Shape::Lines(prepared.as_slice()) )
+                    .pen(Some(Color::Red));
+                for i in &marks {
+                    chart.marker(*i as f32);
+                }
+                chart.nice();
+            },
+            Canvas::Sparkline(width) => plot::print_sparkline("", &values, &data.unit, width)
+        }
+        println!();
+    }
+
+    if marks.is_empty() {
+        println!("No anomalies found in the last {} points", values.len());
+    } else {
+        table.printstd();
+    }
+
+    0
+}
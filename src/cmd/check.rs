@@ -0,0 +1,93 @@
+use do_error;
+use check::{self, Status, Threshold};
+use data::DataSource;
+use duration;
+use stats::{self, Aggregation};
+
+use chrono::Utc;
+
+/// Evaluates the latest value, or `agg` over the last `over` seconds, and
+/// prints a Nagios-style status line. Returns the exit code.
+///
+/// Thresholds and the aggregation default to those stored for the series.
+pub fn check(
+    db: &DataSource,
+    series: &str,
+    warn: Option<Threshold>,
+    crit: Option<Threshold>,
+    over: Option<u32>,
+    agg: Option<Aggregation>
+) -> i32 {
+    let meta = match db.get_series(series) {
+        Ok(Some(s)) => s.meta,
+        Ok(None) => {
+            println!("{} - series {} not found", Status::Unknown, series);
+            return Status::Unknown as i32;
+        },
+        Err(e) => {
+            do_error("Error querying series data", e);
+            println!("{} - error querying {}", Status::Unknown, series);
+            return Status::Unknown as i32;
+        }
+    };
+
+    let (warn, crit) = (warn.or(meta.warn), crit.or(meta.crit));
+    let agg = agg.or(meta.aggregation).unwrap_or(Aggregation::Avg);
+
+    if warn.is_none() && crit.is_none() {
+        println!("{} - no thresholds for {}, use --warn and/or --crit", Status::Unknown, series);
+        return Status::Unknown as i32;
+    }
+
+    let result = match over {
+        Some(secs) => {
+            let now = Utc::now().timestamp() as u32;
+            db.range(series, now.saturating_sub(secs), now)
+        },
+        None => db.series(series, 1)
+    };
+
+    let data = match result {
+        Ok(Some(d)) => d,
+        Ok(None) => {
+            println!("{} - series {} not found", Status::Unknown, series);
+            return Status::Unknown as i32;
+        },
+        Err(e) => {
+            do_error("Error querying series data", e);
+            println!("{} - error querying {}", Status::Unknown, series);
+            return Status::Unknown as i32;
+        }
+    };
+
+    let values = data.measurements.iter()
+        .rev()
+        .map(|m| m.measurement)
+        .collect::<Vec<_>>();
+
+    let (label, value) = match over {
+        Some(secs) => (format!("{}({})", agg, duration::format(secs)), stats::aggregate(&values, agg)),
+        None => ("last".to_string(), values.last().cloned())
+    };
+
+    let value = match value {
+        Some(v) => v,
+        None => {
+            println!("{} - no data for {} {}", Status::Unknown, series, label);
+            return Status::Unknown as i32;
+        }
+    };
+
+    let status = Status::evaluate(value, warn, crit);
+
+    let unit = if data.unit.is_empty() { String::new() } else { format!(" {}", data.unit) };
+    let limit = |t: Option<Threshold>| t.map(|t| t.value().to_string()).unwrap_or_default();
+
+    println!(
+        "{} - {} {} = {}{} | '{}'={}{};{};{}",
+        status, series, label, meta.format(value), unit,
+        series, value, check::uom(&data.unit).unwrap_or(""), limit(warn), limit(crit)
+    );
+
+    status as i32
+}
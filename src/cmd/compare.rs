@@ -0,0 +1,161 @@
+use do_error;
+use cmd::plot::{self, Canvas};
+use data::{DataSource, Measurement, Series};
+use duration;
+use stats::{self, Summary};
+use textplots::{Color, Shape, Plot};
+
+use prettytable::Table;
+use chrono::{Local, TimeZone, Utc};
+
+fn fetch(db: &DataSource, series: &str, from: u32, to: u32) -> Option<Series> {
+    match db.range(series, from, to) {
+        Ok(s) => s,
+        Err(e) => {
+            do_error("Error querying series data", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn format_ts(ts: u32) -> String {
+    Local.timestamp(ts.into(), 0).format("%Y-%m-%d %H:%M").to_string()
+}
+
+/// Picks one statistic out of a summary.
+type Stat = fn(&Summary) -> f64;
+
+/// Points as (offset into the window, value), oldest first. Virtual series
+/// are bucketed, so their first point can fall before the window starts.
+fn prepare(measurements: &[Measurement], start: u32, scale: f32) -> Vec<(f32, f32)> {
+    measurements.iter()
+        .rev()
+        .filter(|m| m.timestamp >= start)
+        .map(|m| ((m.timestamp - start) as f32 / scale, m.measurement as f32))
+        .collect()
+}
+
+/// Compares the last `window` seconds against the same length of time
+/// `offset` seconds earlier.
+pub fn compare(db: &DataSource, series: &str, window: u32, offset: Option<u32>, color: bool) -> i32 {
+    if window == 0 {
+        eprintln!("--window must be longer than 0s");
+        return 1;
+    }
+
+    let offset = offset.unwrap_or(window);
+    let now = Utc::now().timestamp() as u32;
+
+    let (start, prev_start) = (now.saturating_sub(window), now.saturating_sub(window.saturating_add(offset)));
+    // Windows are inclusive, so keep a point on the boundary out of one of them
+    let prev_end = now.saturating_sub(offset).saturating_sub(1);
+
+    let current = match fetch(db, series, start, now) {
+        Some(s) => s,
+        None => {
+            println!("Series not found");
+            return 1;
+        }
+    };
+    let previous = fetch(db, series, prev_start, prev_end).unwrap();
+
+    println!("# Series: {}", current.title());
+    println!("# Current:  {} - {}", format_ts(start), format_ts(now));
+    println!("# Previous: {} - {}\n", format_ts(prev_start), format_ts(prev_end));
+
+    let values = |s: &Series| s.measurements.iter().rev().map(|m| m.measurement).collect::<Vec<_>>();
+    let (now_values, prev_values) = (values(&current), values(&previous));
+
+    if now_values.is_empty() && prev_values.is_empty() {
+        println!("No data in either window");
+        return 0;
+    }
+
+    let (scale, x_unit) = if window >= 2 * 86_400 {
+        (86_400.0, "days")
+    } else if window >= 2 * 3600 {
+        (3600.0, "hours")
+    } else {
+        (60.0, "minutes")
+    };
+
+    let now_points = prepare(&current.measurements, start, scale);
+    let prev_points = prepare(&previous.measurements, prev_start, scale);
+
+    // Both windows on one scale, so set it up front
+    let ys = now_points.iter().chain(prev_points.iter()).map(|p| p.1);
+    let (ymin, ymax) = ys.fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), y| (lo.min(y), hi.max(y)));
+
+    match plot::canvas(None, None) {
+        Canvas::Chart(width, height) => {
+            plot::chart(width, height, 0.0, window as f32 / scale, &current.unit, color)
+                .y_range(ymin, ymax)
+                .pen(Some(Color::Grey))
+                .lineplot( Shape::Steps(prev_points.as_slice()) )
+                .pen(Some(Color::Cyan))
+                .lineplot( Shape::Lines(now_points.as_slice()) )
+                .nice();
+            println!(
+                "X axis: {} into each window of {}, previous window drawn as steps\n",
+                x_unit, duration::format(window)
+            );
+        },
+        Canvas::Sparkline(width) => {
+            plot::print_sparkline("previous ", &prev_values, &current.unit, width);
+            plot::print_sparkline("current  ", &now_values, &current.unit, width);
+            println!();
+        }
+    }
+
+    let (now_stats, prev_stats) = (Summary::of(&now_values), Summary::of(&prev_values));
+
+    let mut table = Table::new();
+    table.add_row(row!["STAT", "PREVIOUS", "CURRENT", "CHANGE"]);
+
+    let stats: [(&str, usize, Stat); 7] = [
+        ("count", 0, |s| s.count as f64),
+        ("sum", 2, |s| s.sum),
+        ("mean", 2, |s| s.mean),
+        ("median", 2, |s| s.median),
+        ("min", 2, |s| s.min),
+        ("max", 2, |s| s.max),
+        ("last", 2, |s| s.last)
+    ];
+
+    for &(name, precision, get) in stats.iter() {
+        // Counts are whole, the rest follow the series' precision
+        let precision = if name == "count" { precision } else { current.meta.precision.unwrap_or(precision) };
+        let before = prev_stats.as_ref().map(get);
+        let after = now_stats.as_ref().map(get);
+        let show = |v: Option<f64>| v.map(|v| format!("{:.*}", precision, v)).unwrap_or_else(|| "-".into());
+
+        let change = match (before, after) {
+            (Some(b), Some(a)) => stats::pct_change(b, a)
+                .map(|c| format!("{:+.1}%", c))
+                .unwrap_or_else(|| "-".into()),
+            _ => "-".into()
+        };
+
+        table.add_row(row![name, show(before), show(after), change]);
+    }
+
+    table.printstd();
+
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(timestamp: u32, measurement: f64) -> Measurement {
+        Measurement { timestamp, run: 0, measurement }
+    }
+
+    #[test]
+    fn drops_points_before_the_window() {
+        // Newest first, as they come from the database
+        let points = vec![point(1_180, 3.0), point(1_060, 2.0), point(600, 1.0)];
+        assert_eq!(vec![(1.0, 2.0), (3.0, 3.0)], prepare(&points, 1_000, 60.0));
+    }
+}
@@ -0,0 +1,187 @@
+use do_error;
+use duration;
+use transform;
+use units;
+use cli::PlotStyle;
+use config::{Dashboard, Panel, SeriesDisplay, Settings};
+use data::{DataSource, Series};
+use stats::{self, Aggregation};
+use textplots::{Color, Plot};
+use cmd::plot;
+
+use chrono::Local;
+use rusqlite::Result;
+use termion;
+
+use std::io;
+
+/// Space between panels
+const GUTTER: &str = "  ";
+
+/// Room to the right of a chart for the Y-axis labels
+const LABEL_WIDTH: usize = 10;
+
+/// Width of `text` on screen, skipping ANSI colour codes.
+fn visible_width(text: &str) -> usize {
+    let mut width = 0;
+    let mut escape = false;
+
+    for c in text.chars() {
+        match (escape, c) {
+            (false, '\x1b') => escape = true,
+            (false, _) => width += 1,
+            (true, 'm') => escape = false,
+            (true, _) => {}
+        }
+    }
+
+    width
+}
+
+/// Fetches a panel's series, limited to its range and bucketed if asked.
+fn fetch(db: &DataSource, panel: &Panel) -> Result<Option<Series>> {
+    let data = match panel.range {
+        Some(range) => {
+            let now = Local::now().timestamp() as u32;
+            db.range(&panel.series, now.saturating_sub(range), now)?
+        },
+        None => db.series(&panel.series, panel.points)?
+    };
+
+    Ok(data.map(|mut data| {
+        if let Some(bucket) = panel.bucket {
+            let agg = panel.aggregation.or(data.meta.aggregation).unwrap_or(Aggregation::Avg);
+            transform::bucket(&mut data, bucket, agg);
+        }
+        data
+    }))
+}
+
+/// The line above a panel: its title, range and, if set, the aggregate over
+/// everything shown.
+fn heading(panel: &Panel, data: &Series) -> String {
+    let mut heading = panel.title.clone().unwrap_or_else(|| data.title().to_string());
+
+    if let Some(range) = panel.range {
+        heading.push_str(&format!(" (last {})", duration::format(range)));
+    }
+
+    if let Some(agg) = panel.aggregation {
+        let values = data.measurements.iter().map(|m| m.measurement).collect::<Vec<_>>();
+        if let Some(value) = stats::aggregate(&values, agg) {
+            heading.push_str(&format!("  {} {}", agg, units::format(value, &data.unit)));
+        }
+    }
+
+    heading
+}
+
+/// A panel as lines of text, a chart `width` dots wide.
+fn render(panel: &Panel, data: &Series, display: SeriesDisplay, width: u32, height: u32, color: bool) -> Vec<String> {
+    let mut points = data.measurements.iter()
+        .rev()
+        .enumerate()
+        .map(|(i, m)| (i as f32, m.measurement as f32))
+        .collect::<Vec<_>>();
+    // A point at the origin keeps zero on the Y-axis, like `plot`
+    points.insert(0, (0.0, 0.0));
+
+    let mut chart = plot::chart(width, height, 0.0, points.len() as f32, &data.unit, color);
+    let style = panel.style.or(display.style).unwrap_or(PlotStyle::Line);
+    chart.pen(Some(display.color.unwrap_or(Color::Cyan)))
+        .lineplot(plot::shape(style, &points));
+
+    let mut lines = vec![heading(panel, data)];
+    lines.extend(chart.nice_lines());
+    lines
+}
+
+/// Shows the named dashboard from the config file: a grid of small charts,
+/// or a sparkline per panel when there's no room (or no terminal).
+pub fn dash(db: &DataSource, settings: &Settings, name: &str, color: bool) -> i32 {
+    let dashboard = match settings.dashboards.get(name) {
+        Some(d) => d,
+        None => {
+            let names = settings.dashboards.keys().cloned().collect::<Vec<_>>();
+            if names.is_empty() {
+                eprintln!("No dashboards configured");
+            } else {
+                eprintln!("No dashboard named '{}', expected one of {}", name, names.join(", "));
+            }
+            return 1;
+        }
+    };
+
+    let cols = if termion::is_tty(&io::stdout()) {
+        termion::terminal_size().ok().map(|(c, _)| c as usize)
+    } else {
+        None
+    };
+
+    let mut series = vec![];
+    for panel in &dashboard.panels {
+        match fetch(db, panel) {
+            Ok(data) => series.push(data),
+            Err(e) => {
+                do_error("Error querying series data", e);
+                return 1;
+            }
+        }
+    }
+
+    // Room for each chart once the gutters and labels are taken out
+    let columns = dashboard.columns.max(1);
+    let panel_width = cols.map(|c| c.saturating_sub(GUTTER.len() * (columns - 1)) / columns);
+    let chart_width = panel_width.map(|w| (w.saturating_sub(LABEL_WIDTH) * 2) as u32);
+
+    match chart_width {
+        Some(width) if width >= 32 && dashboard.height >= 32 => grid(settings, dashboard, &series, width, color),
+        _ => {
+            let width = cols.map(|c| c.saturating_sub(40).max(8)).unwrap_or(60);
+            for (panel, data) in dashboard.panels.iter().zip(&series) {
+                match data {
+                    Some(data) => {
+                        let values = data.measurements.iter().rev().map(|m| m.measurement).collect::<Vec<_>>();
+                        plot::print_sparkline(&format!("{}  ", heading(panel, data)), &values, &data.unit, width);
+                    },
+                    None => println!("{}  (series not found)", panel.series)
+                }
+            }
+        }
+    }
+
+    0
+}
+
+fn grid(settings: &Settings, dashboard: &Dashboard, series: &[Option<Series>], width: u32, color: bool) {
+    let panels = dashboard.panels.iter()
+        .zip(series)
+        .map(|(panel, data)| match data {
+            Some(data) => render(panel, data, settings.display(&panel.series), width, dashboard.height, color),
+            None => vec![panel.series.clone(), "(series not found)".into()]
+        })
+        .collect::<Vec<_>>();
+
+    let columns = dashboard.columns.max(1);
+    for row in panels.chunks(columns) {
+        let height = row.iter().map(|p| p.len()).max().unwrap_or(0);
+        let widths = row.iter()
+            .map(|p| p.iter().map(|l| visible_width(l)).max().unwrap_or(0))
+            .collect::<Vec<_>>();
+
+        for i in 0..height {
+            let line = row.iter()
+                .zip(&widths)
+                .map(|(panel, width)| {
+                    let text = panel.get(i).map(|l| l.as_str()).unwrap_or("");
+                    format!("{}{}", text, " ".repeat(width - visible_width(text)))
+                })
+                .collect::<Vec<_>>()
+                .join(GUTTER);
+
+            println!("{}", line.trim_end());
+        }
+
+        println!();
+    }
+}
@@ -0,0 +1,37 @@
+use do_error;
+use prometheus;
+use cli::ExportFormat;
+use data::DataSource;
+use transform::Transform;
+
+use std::fs;
+use std::path::PathBuf;
+
+pub fn export(db: &DataSource, format: ExportFormat, transforms: &[Transform], out: Option<PathBuf>) {
+    let body = match format {
+        ExportFormat::Prometheus => prometheus::render(db, transforms)
+    };
+
+    let body = match body {
+        Ok(b) => b,
+        Err(e) => {
+            do_error("Error exporting series", e);
+            std::process::exit(1);
+        }
+    };
+
+    let out = match out {
+        Some(path) => path,
+        None => {
+            print!("{}", body);
+            return;
+        }
+    };
+
+    // Write then rename so collectors never see a partial file
+    let tmp = out.with_extension("tmp");
+    if let Err(e) = fs::write(&tmp, body).and_then(|_| fs::rename(&tmp, &out)) {
+        do_error(&format!("Error writing {}", out.display()), e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,147 @@
+use do_error;
+use data::DataSource;
+
+use std::io::{BufRead, BufReader};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+
+/// A single `metric.path value timestamp` line.
+struct Point {
+    path: String,
+    value: f64,
+    timestamp: Option<u32>
+}
+
+/// Parses a plaintext protocol line. A missing or negative timestamp means
+/// "now", the same as carbon. Fractions of a second are dropped.
+fn parse_line(line: &str) -> Result<Point, String> {
+    let mut fields = line.split_whitespace();
+
+    let path = fields.next().ok_or("empty line")?;
+    let value = fields.next()
+        .ok_or("missing value")?
+        .parse::<f64>()
+        .map_err(|e| format!("bad value: {}", e))?;
+
+    let timestamp = match fields.next() {
+        Some(ts) => {
+            let ts = ts.parse::<f64>().map_err(|e| format!("bad timestamp: {}", e))?;
+            if ts.is_nan() || ts > f64::from(u32::MAX) {
+                return Err(format!("timestamp {} out of range", ts));
+            } else if ts < 0.0 {
+                None
+            } else {
+                Some(ts.trunc() as u32)
+            }
+        },
+        None => None
+    };
+
+    Ok(Point { path: path.into(), value, timestamp })
+}
+
+fn handle_client(stream: TcpStream, tx: Sender<Point>) {
+    let peer = stream.peer_addr().map(|a| a.to_string()).unwrap_or_default();
+    debug!("Graphite client connected: {}", peer);
+
+    for line in BufReader::new(stream).lines() {
+        let line = match line {
+            Ok(l) => l,
+            Err(e) => {
+                warn!("Error reading from {}: {}", peer, e);
+                break;
+            }
+        };
+
+        let line = line.trim();
+        if line.is_empty() { continue; }
+
+        match parse_line(line) {
+            Ok(point) => {
+                if tx.send(point).is_err() { break; }
+            },
+            Err(e) => warn!("Invalid Graphite line '{}': {}", line, e)
+        }
+    }
+
+    debug!("Graphite client disconnected: {}", peer);
+}
+
+pub fn listen(db: &DataSource, addr: &str, create: bool) {
+    let listener = match TcpListener::bind(addr) {
+        Ok(l) => l,
+        Err(e) => {
+            do_error(&format!("Unable to bind {}", addr), e);
+            std::process::exit(1);
+        }
+    };
+
+    info!("Graphite listener on {}", addr);
+
+    // Clients get a thread each, the DataSource stays on this one
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let tx = tx.clone();
+                    thread::spawn(move || handle_client(stream, tx));
+                },
+                Err(e) => warn!("Error accepting connection: {}", e)
+            }
+        }
+    });
+
+    for point in rx {
+        match db.unwritable(&point.path, create) {
+            Ok(Some(reason)) => warn!("Skipping Graphite metric: {}", reason),
+            Ok(None) => {
+                if let Err(e) = db.measure_at(&point.path, point.value, point.timestamp, &[], create) {
+                    do_error(&format!("Error adding measurement to {}", point.path), e);
+                }
+            },
+            Err(e) => do_error(&format!("Error looking up {}", point.path), e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_path_value_and_timestamp() {
+        let point = parse_line("servers.web1.load 0.5 1700000000").unwrap();
+        assert_eq!("servers.web1.load", point.path);
+        assert_eq!(0.5, point.value);
+        assert_eq!(Some(1700000000), point.timestamp);
+    }
+
+    #[test]
+    fn missing_or_negative_timestamp_is_now() {
+        assert_eq!(None, parse_line("load 1").unwrap().timestamp);
+        assert_eq!(None, parse_line("load 1 -1").unwrap().timestamp);
+    }
+
+    #[test]
+    fn drops_fractions_of_a_second() {
+        assert_eq!(Some(1700000000), parse_line("load 1 1700000000.9").unwrap().timestamp);
+    }
+
+    #[test]
+    fn rejects_timestamps_past_u32() {
+        assert!(parse_line("load 1 4294967296").is_err());
+        assert!(parse_line("load 1 inf").is_err());
+        assert!(parse_line("load 1 NaN").is_err());
+        assert_eq!(Some(u32::MAX), parse_line("load 1 4294967295").unwrap().timestamp);
+    }
+
+    #[test]
+    fn rejects_malformed_lines() {
+        assert!(parse_line("").is_err());
+        assert!(parse_line("load").is_err());
+        assert!(parse_line("load x").is_err());
+        assert!(parse_line("load 1 soon").is_err());
+    }
+}
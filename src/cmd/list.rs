@@ -0,0 +1,70 @@
+use do_error;
+use data::{self, DataSource};
+
+use prettytable::Table;
+use chrono::{Local, TimeZone};
+
+use std::path::Path;
+
+/// Adds a row per series in `db`, first naming the file if there is one.
+fn rows(table: &mut Table, db: &DataSource, file: Option<&str>) -> ::rusqlite::Result<()> {
+    let mut list = db.list_series()?;
+    list.sort_by(|a, b| a.name.cmp(&b.name));
+
+    for series in list {
+        let (points, last) = db.summary(series.id)?;
+        let last = match last {
+            Some(ts) => Local.timestamp(ts.into(), 0).format("%Y-%m-%d %H:%M:%S").to_string(),
+            None => "-".to_string()
+        };
+
+        let mut row = vec![series.name, series.unit, points.to_string(), last];
+        if let Some(file) = file {
+            row.insert(0, file.to_string());
+        }
+        table.add_row(row.into());
+    }
+
+    Ok(())
+}
+
+/// Prints the series in the data file, or in every data file under
+/// `data_root` with `all_files`.
+pub fn list(db: &DataSource, data_root: &Path, all_files: bool) -> i32 {
+    let mut table = Table::new();
+
+    if !all_files {
+        table.add_row(row!["NAME", "UNIT", "POINTS", "LAST UPDATED"]);
+        if let Err(e) = rows(&mut table, db, None) {
+            do_error("Error getting series list", e);
+            return 1;
+        }
+    } else {
+        let files = match data::files(data_root) {
+            Ok(f) => f,
+            Err(e) => {
+                do_error("Error reading data path", e);
+                return 1;
+            }
+        };
+
+        table.add_row(row!["FILE", "NAME", "UNIT", "POINTS", "LAST UPDATED"]);
+        for file in &files {
+            let result = DataSource::new(data_root, Path::new(file))
+                .and_then(|db| rows(&mut table, &db, Some(file)));
+
+            if let Err(e) = result {
+                do_error(&format!("Error getting series list from {}", file), e);
+                return 1;
+            }
+        }
+    }
+
+    if table.len() > 1 {
+        table.printstd();
+    } else {
+        println!("No series");
+    }
+
+    0
+}
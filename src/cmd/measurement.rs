@@ -0,0 +1,66 @@
+use do_error;
+use influx;
+use cli::BulkFormat;
+use data::DataSource;
+
+use std::io::{self, BufRead};
+
+fn parse_kv(line: &str) -> Option<(String, f64)> {
+    let idx = match line.find('=') {
+        Some(i) => i,
+        None => {
+            warn!("Invalid input format: '{}'", line);
+            return None;
+        }
+    };
+
+    let (series, value) = line.split_at(idx);
+    let value = match value.trim_start_matches('=').parse::<f64>() {
+        Ok(v) => v,
+        Err(e) => {
+            do_error("Error parsing value", e);
+            std::process::exit(1);
+        }
+    };
+
+    Some((series.into(), value))
+}
+
+pub fn bulk(db: &DataSource, format: BulkFormat, create: bool) {
+    let stdin = io::stdin();
+    let stdin = stdin.lock();
+
+    stdin.lines()
+        .for_each(|line| {
+            let line = line.unwrap();
+
+            if line.is_empty() { return; }
+
+            match format {
+                BulkFormat::KeyValue => {
+                    if let Some((series, value)) = parse_kv(&line) {
+                        record(db, &series, value, None, &[], create);
+                    }
+                },
+                BulkFormat::Influx => {
+                    if line.starts_with('#') { return; }
+
+                    match influx::parse(&line) {
+                        Ok(point) => {
+                            for (series, value) in point.series() {
+                                record(db, &series, value, point.timestamp, &point.tags, create);
+                            }
+                        },
+                        Err(e) => warn!("Invalid line protocol '{}': {}", line, e)
+                    }
+                }
+            }
+        });
+}
+
+fn record(db: &DataSource, series: &str, value: f64, timestamp: Option<u32>, tags: &[(String, String)], create: bool) {
+    if let Err(e) = db.measure_at(series, value, timestamp, tags, create) {
+        do_error(&format!("Error adding measurement to {}", series), e);
+        std::process::exit(1);
+    }
+}
@@ -1,2 +1,16 @@
 pub mod series;
 pub mod measurement;
+pub mod statsd;
+pub mod graphite;
+pub mod serve;
+pub mod export;
+pub mod scrape;
+pub mod check;
+pub mod anomalies;
+pub mod plot;
+pub mod compare;
+pub mod spark;
+pub mod report;
+pub mod dash;
+pub mod tree;
+pub mod list;
@@ -0,0 +1,839 @@
+use do_error;
+use image::{self, Figure, Layer, Mark};
+use sparkline;
+use units;
+use cli::PlotStyle;
+use menu::Menu;
+use data::{self, DataSource, Series};
+use expr::Expr;
+use transform::{self, Transform};
+use trend::{Model, Trend};
+use check::Threshold;
+use config::Settings;
+use textplots::{Chart, Color, Shape, Plot};
+use textplots::utils;
+
+use prettytable::Table;
+use chrono::{Local, TimeZone};
+use termion;
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// How to draw a plot, beyond what to plot.
+pub struct Options<'a> {
+    /// Show a table of the points as well
+    pub table: bool,
+    pub transforms: Vec<Transform>,
+    /// Overrides the series' display settings
+    pub style: Option<PlotStyle>,
+    /// Fit and overlay a trend line
+    pub trend: Option<Model>,
+    /// Value to estimate the arrival time of, along the trend
+    pub target: Option<f64>,
+    /// Number of points to project the trend forward, 10 unless set
+    pub forecast: Option<usize>,
+    /// Draw gridlines at the Y-axis ticks
+    pub grid: bool,
+    /// Thresholds to mark, shaded in colour
+    pub warn: Option<Threshold>,
+    pub crit: Option<Threshold>,
+    /// Horizontal reference lines
+    pub hlines: Vec<f32>,
+    /// Fixed Y-axis ends
+    pub ymin: Option<f32>,
+    pub ymax: Option<f32>,
+    /// Fit the Y-axis to the data rather than including zero
+    pub no_zero: bool,
+    /// Logarithmic Y-axis
+    pub log: bool,
+    /// Chart size in braille dots (2 per column, 4 per row), instead of
+    /// fitting the terminal
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// Save as an image rather than printing
+    pub out: Option<PathBuf>,
+    /// Print in colour
+    pub color: bool,
+    /// For each series' display settings
+    pub settings: &'a Settings
+}
+
+/// How a series is drawn: the flags, then its display settings, then its
+/// metadata, then the defaults.
+struct Look {
+    style: PlotStyle,
+    color: Color,
+    ymin: Option<f32>,
+    ymax: Option<f32>,
+    warn: Option<Threshold>,
+    crit: Option<Threshold>
+}
+
+impl Look {
+    fn of(series: &Series, opts: &Options) -> Look {
+        let display = opts.settings.display(&series.name);
+        let meta = &series.meta;
+
+        Look {
+            style: opts.style.or(display.style).unwrap_or(PlotStyle::Line),
+            color: display.color.unwrap_or(Color::Cyan),
+            ymin: opts.ymin.or(display.ymin).or(meta.min.map(|v| v as f32)),
+            ymax: opts.ymax.or(display.ymax).or(meta.max.map(|v| v as f32)),
+            warn: opts.warn.or(meta.warn),
+            crit: opts.crit.or(meta.crit)
+        }
+    }
+}
+
+/// What there's room to draw.
+pub enum Canvas {
+    /// A full chart, width and height in braille dots
+    Chart(u32, u32),
+    /// A sparkline, at most this many characters wide
+    Sparkline(usize)
+}
+
+/// Sizes a chart from the overrides or the terminal. Falls back to a
+/// sparkline when the terminal is too narrow for `Chart` (which needs 32
+/// dots each way) or when stdout isn't a terminal at all.
+pub fn canvas(width: Option<u32>, height: Option<u32>) -> Canvas {
+    let term = if termion::is_tty(&io::stdout()) {
+        termion::terminal_size().ok().map(|(c, r)| (u32::from(c), u32::from(r)))
+    } else {
+        None
+    };
+
+    // Leave columns for the Y-axis labels and rows for the headers
+    let width = match (width, term) {
+        (Some(w), _) => w,
+        (None, Some((cols, _))) => cols.saturating_sub(12) * 2,
+        (None, None) => return Canvas::Sparkline(60)
+    };
+    let height = match (height, term) {
+        (Some(h), _) => h,
+        (None, Some((_, rows))) => (rows.saturating_sub(8) * 4).clamp(32, 60),
+        (None, None) => 60
+    };
+
+    if width < 32 || height < 32 {
+        let cols = term.map(|(c, _)| c as usize).unwrap_or(60);
+        Canvas::Sparkline(cols.saturating_sub(40).max(8))
+    } else {
+        Canvas::Chart(width, height)
+    }
+}
+
+/// A chart labelled and with ticks formatted for values in `unit`.
+pub fn chart(width: u32, height: u32, xmin: f32, xmax: f32, unit: &str, color: bool) -> Chart {
+    let mut chart = Chart::new(width, height, xmin, xmax);
+    chart.color(color);
+
+    if !unit.is_empty() {
+        chart.y_label(unit);
+    }
+
+    let unit = unit.to_string();
+    chart.y_format(move |y| units::format(f64::from(y), &unit));
+
+    chart
+}
+
+/// Prints `values` (oldest first) as a sparkline with the last value and the
+/// range.
+pub fn print_sparkline(label: &str, values: &[f64], unit: &str, width: usize) {
+    let (last, min, max) = match values.last() {
+        Some(last) => (
+            last,
+            values.iter().cloned().fold(f64::INFINITY, f64::min),
+            values.iter().cloned().fold(f64::NEG_INFINITY, f64::max)
+        ),
+        None => {
+            println!("{}(no data)", label);
+            return;
+        }
+    };
+
+    let unit = if unit.is_empty() { String::new() } else { format!(" {}", unit) };
+    println!(
+        "{}{} {}{} ({} .. {})",
+        label, sparkline::render(values, width), last, unit, min, max
+    );
+}
+
+/// Series drawn together get these colours, unless set in the config
+const PALETTE: [Color; 6] = [Color::Cyan, Color::Magenta, Color::Green, Color::Yellow, Color::Blue, Color::Red];
+
+/// Plots each series given, or one picked from a menu. A single name or
+/// pattern plots each match on its own, several are drawn on one chart.
+pub fn plot(db: &DataSource, data_root: &Path, series: &[String], points: u8, opts: &Options) -> i32 {
+    let picked;
+    let series = if series.is_empty() {
+        match db.list_series() {
+            Ok(list) => {
+                let list: Vec<_> = list.iter().map(|s| s.name.as_str()).collect();
+                picked = [Menu::from_tree("Select series to plot:", &list).show().to_owned()];
+                &picked[..]
+            },
+            Err(e) => {
+                do_error("Error getting series list", e);
+                std::process::exit(1);
+            }
+        }
+    } else {
+        series
+    };
+
+    if series.len() > 1 {
+        let single = [
+            ("--table", opts.table),
+            ("--trend", opts.trend.is_some()),
+            ("--target", opts.target.is_some()),
+            ("--forecast", opts.forecast.is_some())
+        ];
+        let given = single.iter().filter(|f| f.1).map(|f| f.0).collect::<Vec<_>>();
+        if !given.is_empty() {
+            eprintln!("Can't use {} with several series", given.join(", "));
+            return 1;
+        }
+    }
+
+    let mut found = vec![];
+    for spec in series {
+        match resolve(db, data_root, spec, points) {
+            Ok(ref data) if data.is_empty() && spec.contains('*') => {
+                eprintln!("No series match '{}'", spec);
+                return 1;
+            },
+            Ok(ref data) if data.is_empty() && series.len() > 1 => {
+                eprintln!("Series {} not found", spec);
+                return 1;
+            },
+            Ok(ref data) if data.is_empty() => {
+                println!("Series not found");
+                return 0;
+            },
+            Ok(data) => found.extend(data),
+            Err(e) => {
+                do_error("Error querying series data", e);
+                return 1;
+            }
+        }
+    }
+
+    if series.len() > 1 {
+        return overlay(found, opts);
+    }
+
+    // Each plot would overwrite the same image
+    if found.len() > 1 && opts.out.is_some() {
+        eprintln!("--out needs a single series, '{}' matches {}", series[0], found.len());
+        return 1;
+    }
+
+    found.into_iter().map(|data| draw(data, opts)).max().unwrap_or(0)
+}
+
+/// Splits the data file off the front of `spec` if it names one of `files`.
+/// Names may have colons in them too, so anything else is all series name.
+fn split_file<'a>(files: &[String], spec: &'a str) -> (Option<&'a str>, &'a str) {
+    match spec.find(':') {
+        Some(i) if files.iter().any(|f| *f == spec[..i]) => (Some(&spec[..i]), &spec[i + 1..]),
+        _ => (None, spec)
+    }
+}
+
+/// Fetches the series named by `spec`, a name or pattern which may start
+/// with the data file to read from instead (eg `app1:metric.a`).
+fn resolve(db: &DataSource, data_root: &Path, spec: &str, points: u8) -> ::rusqlite::Result<Vec<Series>> {
+    let other;
+    let (db, file, pattern) = match split_file(&data::files(data_root).unwrap_or_default(), spec) {
+        (Some(file), pattern) => {
+            other = DataSource::new(data_root, Path::new(file))?;
+            (&other, Some(file), pattern)
+        },
+        (None, pattern) => (db, None, pattern)
+    };
+
+    let names = if pattern.contains('*') {
+        db.matching(pattern)?
+    } else {
+        vec![pattern.to_string()]
+    };
+
+    let mut found = vec![];
+    for name in names {
+        if let Some(mut data) = db.series(&name, points)? {
+            // Tell apart the same series from different files
+            if let Some(file) = file {
+                data.meta.display_name = Some(format!("{}:{}", file, data.title()));
+            }
+            found.push(data);
+        }
+    }
+
+    Ok(found)
+}
+
+/// Draws several series on one chart to compare them, lined up on their
+/// newest points. Trend lines and tables are left to single series.
+fn overlay(mut series: Vec<Series>, opts: &Options) -> i32 {
+    if !limits_ok(opts.ymin, opts.ymax, opts.log) {
+        return 1;
+    }
+
+    for data in &mut series {
+        transform::apply(data, &opts.transforms);
+    }
+
+    let longest = series.iter().map(|s| s.measurements.len()).max().unwrap_or(0);
+    let layers = series.iter()
+        .enumerate()
+        .map(|(i, data)| {
+            let display = opts.settings.display(&data.name);
+            let offset = longest - data.measurements.len();
+            let points = data.measurements.iter()
+                .rev()
+                .enumerate()
+                .map(|(j, m)| ((offset + j) as f32, m.measurement as f32))
+                .collect::<Vec<_>>();
+
+            (
+                data,
+                display.color.unwrap_or(PALETTE[i % PALETTE.len()]),
+                opts.style.or(display.style).unwrap_or(PlotStyle::Line),
+                points
+            )
+        })
+        .collect::<Vec<_>>();
+
+    // A shared unit if there is one
+    let unit = match series.first() {
+        Some(first) if series.iter().all(|s| s.unit == first.unit) => first.unit.clone(),
+        _ => String::new()
+    };
+
+    // Each series' own thresholds unless the flags set them for all
+    let warns = distinct(series.iter().map(|s| opts.warn.or(s.meta.warn)));
+    let crits = distinct(series.iter().map(|s| opts.crit.or(s.meta.crit)));
+
+    let title = series.iter().map(|s| s.title()).collect::<Vec<_>>().join(", ");
+    println!("# Series: {}", title);
+    for &(data, color, _, _) in &layers {
+        let unit = if data.unit.is_empty() { String::new() } else { format!(" ({})", data.unit) };
+        if opts.color {
+            println!("#   {} {}{}", color.paint("━━"), data.title(), unit);
+        } else {
+            println!("#   {}{}: {:?}", data.title(), unit, color);
+        }
+    }
+    println!();
+
+    let canvas = match opts.out {
+        Some(ref out) => {
+            let mut figure = Figure::new(&title, &unit);
+            for &(_, color, style, ref points) in &layers {
+                figure.layers.push(Layer { points: points.clone(), mark: mark(style), color });
+            }
+            marks(&mut figure, None, None, (opts.ymin, opts.ymax), opts);
+            for &warn in &warns {
+                thresholds(&mut figure, Some(warn), None);
+            }
+            for &crit in &crits {
+                thresholds(&mut figure, None, Some(crit));
+            }
+
+            if let Err(code) = save(&figure, out) {
+                return code;
+            }
+            None
+        },
+        None => Some(canvas(opts.width, opts.height))
+    };
+
+    match canvas {
+        Some(Canvas::Chart(width, height)) => {
+            let mut chart = chart(width, height, 0.0, longest.saturating_sub(1).max(1) as f32, &unit, opts.color);
+            chart.grid(opts.grid);
+
+            if opts.log {
+                chart.y_log();
+            } else if opts.no_zero {
+                chart.y_tight();
+            } else {
+                chart.y_range(0.0, 0.0);
+            }
+            chart.y_limits(opts.ymin, opts.ymax);
+
+            // Set the scale up front so every series agrees
+            let ys = layers.iter()
+                .flat_map(|l| l.3.iter().map(|p| p.1))
+                .filter(|y| !opts.log || *y > 0.0);
+            let (ymin, ymax) = ys.fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), y| (lo.min(y), hi.max(y)));
+            if ymin <= ymax {
+                chart.y_range(ymin, ymax);
+            }
+
+            let marks = warns.iter().chain(crits.iter()).map(|t| t.value() as f32);
+            for y in marks.chain(opts.hlines.iter().cloned()) {
+                chart.y_range(y, y);
+            }
+
+            for &(_, color, style, ref points) in &layers {
+                chart.pen(Some(color)).lineplot(shape(style, points));
+            }
+
+            for &warn in &warns {
+                threshold(&mut chart, Some(warn), Color::Yellow);
+            }
+            for &crit in &crits {
+                threshold(&mut chart, Some(crit), Color::Red);
+            }
+
+            chart.pen(Some(Color::Grey));
+            for y in &opts.hlines {
+                chart.hrule(*y);
+            }
+
+            chart.nice();
+        },
+        Some(Canvas::Sparkline(width)) => {
+            let label_width = series.iter().map(|s| s.title().chars().count()).max().unwrap_or(0);
+            for data in &series {
+                let values = data.measurements.iter().rev().map(|m| m.measurement).collect::<Vec<_>>();
+                let label = format!("{:width$}  ", data.title(), width = label_width);
+                print_sparkline(&label, &values, &data.unit, width);
+            }
+        },
+        None => {}
+    }
+
+    0
+}
+
+pub fn plot_expression(db: &DataSource, expression: &str, bucket: u32, points: u8, opts: &Options) -> i32 {
+    let parsed = match Expr::parse(expression) {
+        Ok(e) => e,
+        Err(e) => {
+            eprintln!("Invalid expression: {}", e);
+            return 1;
+        }
+    };
+
+    for name in parsed.series() {
+        match db.get_series(&name) {
+            Ok(Some(_)) => {},
+            Ok(None) => {
+                println!("Series {} not found", name);
+                return 0;
+            },
+            Err(e) => {
+                do_error("Error querying series data", e);
+                return 1;
+            }
+        }
+    }
+
+    match db.derive(expression, bucket, points) {
+        Ok(data) => draw(data, opts),
+        Err(e) => {
+            do_error("Error querying series data", e);
+            1
+        }
+    }
+}
+
+pub fn shape(style: PlotStyle, points: &[(f32, f32)]) -> Shape<'_> {
+    match style {
+        PlotStyle::Line => Shape::Lines(points),
+        PlotStyle::Step => Shape::Steps(points),
+        PlotStyle::Bar => Shape::Bars(points),
+        PlotStyle::Points => Shape::Points(points)
+    }
+}
+
+/// The thresholds given, without repeats.
+fn distinct<I: Iterator<Item = Option<Threshold>>>(thresholds: I) -> Vec<Threshold> {
+    let mut out: Vec<Threshold> = vec![];
+    for t in thresholds.flatten() {
+        if !out.iter().any(|o| o.to_string() == t.to_string()) {
+            out.push(t);
+        }
+    }
+    out
+}
+
+/// Whether the Y-axis limits leave a range to draw, complaining if not.
+fn limits_ok(ymin: Option<f32>, ymax: Option<f32>, log: bool) -> bool {
+    if ymin.iter().chain(ymax.iter()).any(|v| !v.is_finite()) {
+        eprintln!("Y-axis limits have to be finite numbers");
+        return false;
+    }
+
+    if let Some(v) = ymin.iter().chain(ymax.iter()).find(|v| log && **v <= 0.0) {
+        eprintln!("Y-axis limit {} can't be shown with --log, limits have to be above 0", v);
+        return false;
+    }
+
+    match (ymin, ymax) {
+        (Some(lo), Some(hi)) if lo >= hi => {
+            eprintln!("Y-axis minimum {} has to be below the maximum {}", lo, hi);
+            false
+        },
+        _ => true
+    }
+}
+
+/// Marks a threshold with a line, shading the side that breaches it.
+fn threshold(chart: &mut Chart, threshold: Option<Threshold>, color: Color) {
+    let threshold = match threshold {
+        Some(t) => t,
+        None => return
+    };
+
+    let value = threshold.value() as f32;
+    match threshold.above() {
+        Some(true) => { chart.band(value, f32::INFINITY, color); },
+        Some(false) => { chart.band(f32::NEG_INFINITY, value, color); },
+        None => {}
+    }
+
+    chart.pen(Some(color)).hrule(value);
+}
+
+fn draw(mut data: Series, opts: &Options) -> i32 {
+    transform::apply(&mut data, &opts.transforms);
+    let look = Look::of(&data, opts);
+    if !limits_ok(look.ymin, look.ymax, opts.log) {
+        return 1;
+    }
+
+    println!("# Series: {}", data.title());
+    if let Some(ref description) = data.meta.description {
+        println!("# {}", description);
+    }
+    println!();
+
+    let points = data.measurements.iter()
+        .rev()
+        .enumerate()
+        .map(|(i, point)| {
+            ((i) as f32, point.measurement as f32)
+        })
+        .collect::<Vec<_>>();
+    let mut prepared = points.clone();
+    // A point at the origin keeps zero on the Y-axis
+    if !opts.no_zero && !opts.log {
+        prepared.insert(0, (0.0f32, 0.0f32));
+    }
+
+    let values = data.measurements.iter().rev().map(|m| m.measurement).collect::<Vec<_>>();
+    let trend = opts.trend.as_ref().and_then(|model| Trend::fit(model, &values));
+
+    let mut x_width = prepared.len() as f32;
+
+    // Fitted values over the points, then projected past the last one
+    let trend_line = match trend {
+        Some(ref t) => {
+            let last = values.len() - 1;
+            let forecast = opts.forecast.unwrap_or(10);
+            let projected = (1..=forecast).map(|k| ((last + k) as f32, t.project(k as f64) as f32));
+
+            x_width += forecast as f32;
+            t.fitted.iter()
+                .enumerate()
+                .map(|(i, v)| (i as f32, *v as f32))
+                .chain(projected)
+                .collect::<Vec<_>>()
+        },
+        None => vec![]
+    };
+
+    let canvas = match opts.out {
+        Some(ref out) => {
+            if let Err(code) = save(&figure(&data, &points, &trend_line, &look, opts), out) {
+                return code;
+            }
+            None
+        },
+        None => Some(canvas(opts.width, opts.height))
+    };
+
+    match canvas {
+        Some(Canvas::Chart(width, height)) => {
+            let mut chart = chart(width, height, 0.0, x_width, &data.unit, opts.color);
+            chart.grid(opts.grid);
+
+            if opts.log {
+                chart.y_log();
+            } else if opts.no_zero {
+                chart.y_tight();
+            }
+            chart.y_limits(look.ymin, look.ymax);
+
+            if !trend_line.is_empty() {
+                // Set the scale up front so the points and the trend line agree
+                let ys = trend_line.iter().map(|p| p.1);
+                let (ymin, ymax) = ys.fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), y| (lo.min(y), hi.max(y)));
+                chart.y_range(ymin, ymax);
+            }
+
+            // Keep the thresholds and reference lines in view
+            let marks = look.warn.iter().chain(look.crit.iter()).map(|t| t.value() as f32);
+            for y in marks.chain(opts.hlines.iter().cloned()) {
+                chart.y_range(y, y);
+            }
+
+            chart.pen(Some(look.color))
+                .lineplot( shape(look.style, prepared.as_slice()) );
+
+            if !trend_line.is_empty() {
+                chart.pen(Some(Color::Yellow))
+                    .lineplot( Shape::Lines(trend_line.as_slice()) );
+            }
+
+            threshold(&mut chart, look.warn, Color::Yellow);
+            threshold(&mut chart, look.crit, Color::Red);
+
+            chart.pen(Some(Color::Grey));
+            for y in &opts.hlines {
+                chart.hrule(*y);
+            }
+
+            chart.nice();
+        },
+        Some(Canvas::Sparkline(width)) => print_sparkline("", &values, &data.unit, width),
+        None => {}
+    }
+
+    if let Some(ref t) = trend {
+        let unit = if data.unit.is_empty() { String::new() } else { format!(" {}", data.unit) };
+        println!("Trend: {:+.3}{} per point", t.slope(), unit);
+
+        if let Some(target) = opts.target {
+            print_eta(&data, t, target);
+        }
+    }
+
+    if opts.table {
+        let mut table = Table::new();
+        table.add_row(row!["#", "TIMESTAMP", "VALUE"]);
+
+        data.measurements.iter()
+            .enumerate()
+            .for_each(|(i, p)| {
+                let run = data.measurements.len() - i;
+                let ts = Local.timestamp(p.timestamp.into(), 0);
+
+                table.add_row(row![
+                    run,
+                    ts.format("%Y-%m-%d %H:%M:%S"),
+                    data.meta.format(p.measurement)
+                ]);
+            });
+
+        println!(); // newline
+        table.printstd();
+    }
+
+    0
+}
+
+/// The chart `draw` prints, as an image: the same points, trend and marks.
+fn figure(data: &Series, points: &[(f32, f32)], trend_line: &[(f32, f32)], look: &Look, opts: &Options) -> Figure {
+    let mut figure = Figure::new(data.title(), &data.unit);
+
+    if let (Some(newest), Some(oldest)) = (data.measurements.first(), data.measurements.last()) {
+        let time = |ts: u32| Local.timestamp(ts.into(), 0).format("%Y-%m-%d %H:%M:%S");
+        figure.subtitle = Some(format!("{} to {}", time(oldest.timestamp), time(newest.timestamp)));
+    }
+
+    figure.layers.push(Layer { points: points.to_vec(), mark: mark(look.style), color: look.color });
+
+    if !trend_line.is_empty() {
+        figure.layers.push(Layer { points: trend_line.to_vec(), mark: Mark::Line, color: Color::Yellow });
+    }
+
+    marks(&mut figure, look.warn, look.crit, (look.ymin, look.ymax), opts);
+    figure
+}
+
+/// Saves a chart as an image, or returns the exit code if it can't be.
+fn save(figure: &Figure, out: &Path) -> Result<(), i32> {
+    match image::save(figure, out) {
+        Ok(_) => {
+            println!("Saved chart to {}", out.display());
+            Ok(())
+        },
+        Err(ref e) if e.kind() == io::ErrorKind::InvalidInput => {
+            eprintln!("Can't save chart: {}", e);
+            Err(1)
+        },
+        Err(e) => {
+            do_error("Error saving chart", e);
+            Err(1)
+        }
+    }
+}
+
+fn mark(style: PlotStyle) -> Mark {
+    match style {
+        PlotStyle::Line => Mark::Line,
+        PlotStyle::Step => Mark::Step,
+        PlotStyle::Bar => Mark::Bar,
+        PlotStyle::Points => Mark::Points
+    }
+}
+
+/// Adds the thresholds, reference lines and axis settings to a figure.
+fn marks(figure: &mut Figure, warn: Option<Threshold>, crit: Option<Threshold>, ylimits: (Option<f32>, Option<f32>), opts: &Options) {
+    thresholds(figure, warn, crit);
+    figure.hrules.extend(opts.hlines.iter().map(|y| (*y, Color::Grey)));
+
+    figure.ylimits = ylimits;
+    figure.zero = !opts.no_zero;
+    figure.log = opts.log;
+    figure.grid = opts.grid;
+}
+
+/// Marks warning and critical thresholds on a figure with a line each,
+/// shading the side that breaches them.
+pub fn thresholds(figure: &mut Figure, warn: Option<Threshold>, crit: Option<Threshold>) {
+    for &(threshold, color) in &[(warn, Color::Yellow), (crit, Color::Red)] {
+        if let Some(t) = threshold {
+            let value = t.value() as f32;
+            match t.above() {
+                Some(true) => figure.bands.push((value, f32::INFINITY, color)),
+                Some(false) => figure.bands.push((f32::NEG_INFINITY, value, color)),
+                None => {}
+            }
+            figure.hrules.push((value, color));
+        }
+    }
+}
+
+/// Prints when the trend is expected to reach `target`, assuming points keep
+/// arriving at the average interval seen so far.
+fn print_eta(data: &Series, trend: &Trend, target: f64) {
+    let (newest, oldest) = match (data.measurements.first(), data.measurements.last()) {
+        (Some(n), Some(o)) => (n, o),
+        _ => return
+    };
+
+    let unit = if data.unit.is_empty() { String::new() } else { format!(" {}", data.unit) };
+
+    let ahead = match trend.reaches(target) {
+        Some(a) => a,
+        None => {
+            println!("Not expected to reach {}{} at the current trend", target, unit);
+            return;
+        }
+    };
+
+    let interval = f64::from(newest.timestamp - oldest.timestamp) / (data.measurements.len() - 1) as f64;
+    let eta = f64::from(newest.timestamp) + ahead * interval;
+
+    if eta > f64::from(i32::MAX) {
+        println!("Not expected to reach {}{} in the foreseeable future", target, unit);
+        return;
+    }
+
+    let eta = Local.timestamp(eta as i64, 0);
+    println!(
+        "Expected to reach {}{} around {} ({:.1} points away)",
+        target,
+        unit,
+        eta.format("%Y-%m-%d %H:%M:%S"),
+        ahead
+    );
+}
+
+/// Plots the distribution of the last `points` values in `bins` equal bins.
+pub fn histogram(db: &DataSource, series: &str, points: u8, bins: usize, color: bool) -> i32 {
+    let data = match db.series(series, points) {
+        Ok(Some(d)) => d,
+        Ok(None) => {
+            println!("Series not found");
+            return 0;
+        },
+        Err(e) => {
+            do_error("Error querying series data", e);
+            return 1;
+        }
+    };
+
+    if data.measurements.is_empty() {
+        println!("No data for {}", series);
+        return 0;
+    }
+
+    let bins = bins.max(1);
+    let values = data.measurements.iter()
+        .map(|m| (0.0, m.measurement as f32))
+        .collect::<Vec<_>>();
+
+    let min = values.iter().map(|v| v.1).fold(f32::INFINITY, f32::min);
+    let mut max = values.iter().map(|v| v.1).fold(f32::NEG_INFINITY, f32::max);
+    if max <= min {
+        max = min + 1.0;
+    }
+
+    // histogram() leaves out values equal to max, so nudge it past the top
+    let step = (max - min) / bins as f32;
+    let counts = utils::histogram(&values, min, max + step * 1e-3, bins);
+
+    // Bars are drawn between consecutive points at the height of the second
+    let mut bars = vec![(min, 0.0)];
+    bars.extend(counts.iter().map(|&(x, n)| (x + step, n)));
+
+    println!("# Series: {} ({} values)\n", data.name, values.len());
+
+    match canvas(None, None) {
+        Canvas::Chart(width, height) => {
+            chart(width, height, min, max, "", color)
+                .y_label("count")
+                .pen(Some(Color::Cyan))
+                .lineplot( Shape::Bars(bars.as_slice()) )
+                .nice();
+        },
+        Canvas::Sparkline(width) => {
+            let counts = counts.iter().map(|c| f64::from(c.1)).collect::<Vec<_>>();
+            print_sparkline("", &counts, "", width);
+        }
+    }
+
+    let mut table = Table::new();
+    table.add_row(row!["FROM", "TO", "COUNT"]);
+    for &(x, n) in &counts {
+        table.add_row(row![format!("{:.2}", x), format!("{:.2}", x + step), n]);
+    }
+
+    println!();
+    table.printstd();
+
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_known_data_files() {
+        let files = vec!["app1".to_string()];
+        assert_eq!((Some("app1"), "metric.a"), split_file(&files, "app1:metric.a"));
+        assert_eq!((Some("app1"), "metric.*"), split_file(&files, "app1:metric.*"));
+        assert_eq!((None, "trk.log:x"), split_file(&files, "trk.log:x"));
+        assert_eq!((None, "../app1:x"), split_file(&files, "../app1:x"));
+        assert_eq!((None, "metric.a"), split_file(&files, "metric.a"));
+    }
+
+    #[test]
+    fn log_limits_have_to_be_positive() {
+        assert!(limits_ok(Some(0.0), None, false));
+        assert!(limits_ok(Some(1.0), Some(100.0), true));
+        assert!(!limits_ok(Some(0.0), None, true));
+        assert!(!limits_ok(None, Some(-5.0), true));
+        assert!(!limits_ok(Some(5.0), Some(1.0), false));
+    }
+}
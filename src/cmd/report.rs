@@ -0,0 +1,163 @@
+use do_error;
+use glob;
+use cmd::plot;
+use stats::Summary;
+use units;
+use data::{DataSource, Series};
+use image::{svg, Figure, Layer, Mark};
+use textplots::Color;
+
+use chrono::{Local, TimeZone};
+
+use std::fmt::Write;
+use std::fs;
+use std::path::PathBuf;
+
+const STYLE: &str = "\
+body { font-family: sans-serif; color: #282828; max-width: 60em; margin: 2em auto; padding: 0 1em; }
+h1 { margin-bottom: 0; }
+h2 { margin-top: 2em; border-bottom: 1px solid #e1e1e1; }
+table { border-collapse: collapse; margin: 1em 0; }
+th, td { padding: 0.2em 0.8em; text-align: right; border-bottom: 1px solid #e1e1e1; }
+th:first-child, td:first-child { text-align: left; }
+svg { max-width: 100%; height: auto; }
+.meta { color: #5a5a5a; }
+";
+
+fn time(ts: u32) -> String {
+    Local.timestamp(ts.into(), 0).format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+/// A value to the series' precision if it has one, otherwise scaled to suit
+/// its unit.
+fn format_value(data: &Series, value: f64) -> String {
+    match data.meta.precision {
+        Some(_) if data.unit.is_empty() => data.meta.format(value),
+        Some(_) => format!("{} {}", data.meta.format(value), data.unit),
+        None => units::format(value, &data.unit)
+    }
+}
+
+/// A section for one series: its chart and the stats over the points shown.
+fn section(out: &mut String, data: &Series) {
+    writeln!(out, "<h2 id=\"{}\">{}</h2>", svg::escape(&data.name), svg::escape(data.title())).unwrap();
+    if let Some(ref description) = data.meta.description {
+        writeln!(out, "<p>{}</p>", svg::escape(description)).unwrap();
+    }
+
+    let newest = match data.measurements.first() {
+        Some(m) => m,
+        None => {
+            out.push_str("<p class=\"meta\">No data</p>\n");
+            return;
+        }
+    };
+    writeln!(out, "<p class=\"meta\">Last updated {}</p>", time(newest.timestamp)).unwrap();
+
+    let values = data.measurements.iter().rev().map(|m| m.measurement).collect::<Vec<_>>();
+
+    let mut figure = Figure::new(data.title(), &data.unit);
+    figure.width = 720;
+    figure.height = 280;
+    figure.grid = true;
+    figure.layers.push(Layer {
+        points: values.iter().enumerate().map(|(i, v)| (i as f32, *v as f32)).collect(),
+        mark: Mark::Line,
+        color: Color::Cyan
+    });
+    plot::thresholds(&mut figure, data.meta.warn, data.meta.crit);
+    figure.ylimits = (data.meta.min.map(|v| v as f32), data.meta.max.map(|v| v as f32));
+    out.push_str(&svg::render(&figure));
+
+    if let Some(summary) = Summary::of(&values) {
+        let stats = [
+            ("Last", summary.last),
+            ("Mean", summary.mean),
+            ("Median", summary.median),
+            ("Min", summary.min),
+            ("Max", summary.max)
+        ];
+
+        out.push_str("<table>\n");
+        writeln!(out, "<tr><th>Points</th><td>{}</td></tr>", summary.count).unwrap();
+        for (label, value) in &stats {
+            writeln!(
+                out,
+                "<tr><th>{}</th><td title=\"{}\">{}</td></tr>",
+                label, value, svg::escape(&format_value(data, *value))
+            ).unwrap();
+        }
+        out.push_str("</table>\n");
+    }
+}
+
+/// Writes an HTML page with a chart and stats for each series matching any
+/// of `patterns` (or all of them), using the last `points` values of each.
+pub fn report(db: &DataSource, patterns: &[String], points: u8, out: Option<PathBuf>) -> i32 {
+    let list = match db.list_series() {
+        Ok(l) => l,
+        Err(e) => {
+            do_error("Error getting series list", e);
+            return 1;
+        }
+    };
+
+    let names = list.iter()
+        .map(|s| s.name.as_str())
+        .filter(|n| patterns.is_empty() || patterns.iter().any(|p| glob::matches(p, n)))
+        .collect::<Vec<_>>();
+
+    if names.is_empty() {
+        println!("No matching series");
+        return 0;
+    }
+
+    let mut series = vec![];
+    for name in names {
+        match db.series(name, points) {
+            Ok(Some(d)) => series.push(d),
+            Ok(None) => continue,
+            Err(e) => {
+                do_error("Error querying series data", e);
+                return 1;
+            }
+        }
+    }
+
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>trk report</title>\n");
+    writeln!(html, "<style>\n{}</style>\n</head>\n<body>", STYLE).unwrap();
+    html.push_str("<h1>trk report</h1>\n");
+    writeln!(html, "<p class=\"meta\">Generated {}</p>", Local::now().format("%Y-%m-%d %H:%M:%S")).unwrap();
+
+    // An index of the latest values, linking to each section
+    html.push_str("<table>\n<tr><th>Series</th><th>Last</th><th>Updated</th></tr>\n");
+    for data in &series {
+        let name = svg::escape(&data.name);
+        let (last, updated) = match data.measurements.first() {
+            Some(m) => (svg::escape(&format_value(data, m.measurement)), time(m.timestamp)),
+            None => ("-".to_string(), "-".to_string())
+        };
+        writeln!(html, "<tr><td><a href=\"#{}\">{}</a></td><td>{}</td><td>{}</td></tr>", name, name, last, updated).unwrap();
+    }
+    html.push_str("</table>\n");
+
+    for data in &series {
+        section(&mut html, data);
+    }
+
+    html.push_str("</body>\n</html>\n");
+
+    match out {
+        Some(path) => {
+            if let Err(e) = fs::write(&path, html) {
+                do_error(&format!("Error writing {}", path.display()), e);
+                return 1;
+            }
+            println!("Wrote report on {} series to {}", series.len(), path.display());
+        },
+        None => print!("{}", html)
+    }
+
+    0
+}
@@ -0,0 +1,147 @@
+use do_error;
+use http;
+use prometheus::{self, Sample};
+use data::DataSource;
+
+use std::fs;
+use std::io::{self, Read};
+
+/// Reads exposition text from a URL, a file or stdin (`-` or no source).
+fn read_source(source: Option<&str>) -> io::Result<String> {
+    match source {
+        Some(url) if url.starts_with("http://") => http::get(url),
+        Some(path) if path != "-" => fs::read_to_string(path),
+        _ => {
+            let mut buf = String::new();
+            io::stdin().read_to_string(&mut buf)?;
+            Ok(buf)
+        }
+    }
+}
+
+/// Parses a `--label` filter, `key=value` with the value optionally quoted.
+fn parse_filter(filter: &str) -> Result<(String, String), String> {
+    match filter.find('=') {
+        Some(i) if i > 0 => Ok((filter[..i].to_string(), filter[i + 1..].trim_matches('"').to_string())),
+        _ => Err(format!("Invalid label filter '{}', expected key=value", filter))
+    }
+}
+
+/// The series a sample goes in: the metric name followed by each label in
+/// order of key, so every label set is kept apart (eg
+/// `http_requests_total.code=500.method=get`). Label characters that mean
+/// something in a series name (`*`, `:`, `.`) or would need quoting become `_`.
+fn series_name(sample: &Sample) -> String {
+    let mut labels = sample.labels.iter().collect::<Vec<_>>();
+    labels.sort();
+
+    labels.iter().fold(sample.name.clone(), |name, (k, v)| format!("{}.{}={}", name, clean(k), clean(v)))
+}
+
+fn clean(label: &str) -> String {
+    label.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '-' { c } else { '_' })
+        .collect()
+}
+
+fn selected(sample: &Sample, metrics: &[String], labels: &[(String, String)]) -> bool {
+    if !metrics.is_empty() && !metrics.contains(&sample.name) {
+        return false;
+    }
+
+    labels.iter().all(|l| sample.labels.contains(l))
+}
+
+pub fn scrape(db: &DataSource, source: Option<String>, metrics: Vec<String>, labels: Vec<String>, create: bool) {
+    let labels = match labels.iter().map(|l| parse_filter(l)).collect::<Result<Vec<_>, _>>() {
+        Ok(l) => l,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let text = match read_source(source.as_deref()) {
+        Ok(t) => t,
+        Err(e) => {
+            do_error("Error reading metrics", e);
+            std::process::exit(1);
+        }
+    };
+
+    let mut recorded = 0;
+    for line in text.lines() {
+        let sample = match prometheus::parse_line(line) {
+            Some(Ok(s)) => s,
+            Some(Err(e)) => {
+                warn!("Invalid metric line '{}': {}", line, e);
+                continue;
+            },
+            None => continue
+        };
+
+        if !selected(&sample, &metrics, &labels) || !sample.value.is_finite() {
+            continue;
+        }
+
+        let series = series_name(&sample);
+        match db.measure_at(&series, sample.value, sample.timestamp, &sample.labels, create) {
+            Ok(_) => recorded += 1,
+            Err(e) => {
+                do_error(&format!("Error adding measurement to {}", series), e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    debug!("Recorded {} samples", recorded);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(line: &str) -> Sample {
+        prometheus::parse_line(line).unwrap().unwrap()
+    }
+
+    fn filters(input: &[&str]) -> Vec<(String, String)> {
+        input.iter().map(|f| parse_filter(f).unwrap()).collect()
+    }
+
+    #[test]
+    fn parses_filters() {
+        assert_eq!(("code".to_string(), "500".to_string()), parse_filter("code=500").unwrap());
+        assert_eq!(("path".to_string(), "/a=b".to_string()), parse_filter("path=\"/a=b\"").unwrap());
+        assert!(parse_filter("code").is_err());
+        assert!(parse_filter("=500").is_err());
+    }
+
+    #[test]
+    fn one_series_per_label_set() {
+        let ok = sample(r#"http_requests_total{method="get",code="200"} 10"#);
+        let failed = sample(r#"http_requests_total{code="500",method="get"} 1"#);
+
+        assert_eq!("http_requests_total.code=200.method=get", series_name(&ok));
+        assert_eq!("http_requests_total.code=500.method=get", series_name(&failed));
+        assert_eq!("up", series_name(&sample("up 1")));
+    }
+
+    #[test]
+    fn cleans_label_values() {
+        let s = sample(r#"req_total{path="/api/*",host="db:5432",le="0.5",msg="not found"} 1"#);
+        assert_eq!("req_total.host=db_5432.le=0_5.msg=not_found.path=_api__", series_name(&s));
+    }
+
+    #[test]
+    fn selects_by_metric_and_labels() {
+        let s = sample(r#"http_requests_total{code="500",method="get"} 1"#);
+        let metric = vec!["http_requests_total".to_string()];
+
+        assert!(selected(&s, &[], &[]));
+        assert!(selected(&s, &metric, &filters(&["code=500"])));
+        assert!(selected(&s, &metric, &filters(&["code=500", "method=get"])));
+        assert!(!selected(&s, &metric, &filters(&["code=200"])));
+        assert!(!selected(&s, &["up".to_string()], &[]));
+    }
+}
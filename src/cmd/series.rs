@@ -1,10 +1,38 @@
 use do_error;
 use menu::Menu;
 use data::DataSource;
+use expr::Expr;
+use meta::Meta;
 
+use prettytable::Table;
 use read_input::input_new;
+use termion;
 
-pub fn create(db: &DataSource, name: Option<String>, unit: Option<String>) {
+use std::io;
+
+/// Whether evaluating `expr` would end up reading `target`, following
+/// virtual series through their own expressions.
+fn references(db: &DataSource, expr: &Expr, target: &str, depth: u8) -> bool {
+    if depth > 32 {
+        return true;
+    }
+
+    expr.series().iter().any(|name| {
+        if name == target {
+            return true;
+        }
+
+        match db.get_series(name) {
+            Ok(Some(s)) => match s.expression.map(|e| Expr::parse(&e.expr)) {
+                Some(Ok(inner)) => references(db, &inner, target, depth + 1),
+                _ => false
+            },
+            _ => false
+        }
+    })
+}
+
+pub fn create(db: &DataSource, name: Option<String>, unit: Option<String>, expression: Option<String>, bucket: u32) {
     let name = name.unwrap_or_else(|| {
         input_new().msg("Series Name: ").get()
     });
@@ -13,7 +41,33 @@ pub fn create(db: &DataSource, name: Option<String>, unit: Option<String>) {
         input_new().msg("Input Unit (eg ms, bps): ").get()
     });
 
-    match db.create_series(&name, &unit) {
+    let result = match expression {
+        Some(expression) => {
+            let parsed = match Expr::parse(&expression) {
+                Ok(e) => e,
+                Err(e) => {
+                    eprintln!("Invalid expression: {}", e);
+                    std::process::exit(1);
+                }
+            };
+
+            if references(db, &parsed, &name, 0) {
+                eprintln!("Expression for {} refers back to itself", name);
+                std::process::exit(1);
+            }
+
+            for input in parsed.series() {
+                if let Ok(None) = db.get_series(&input) {
+                    eprintln!("Warning: {} doesn't exist (yet)", input);
+                }
+            }
+
+            db.create_virtual_series(&name, &unit, &expression, bucket)
+        },
+        None => db.create_series(&name, &unit)
+    };
+
+    match result {
         Ok(series) => println!("Created {}", series.name),
         Err(e) => {
             do_error("Error creating series", e);
@@ -29,7 +83,7 @@ pub fn delete(db: &DataSource, name: Option<String>) {
             match db.list_series() {
                 Ok(list) => {
                     let list: Vec<_> = list.iter().map(|s| s.name.as_str()).collect();
-                    Menu::from_vec("Select name to delete:", &list).show().to_owned()
+                    Menu::from_tree("Select name to delete:", &list).show().to_owned()
                 },
                 Err(e) => {
                     do_error("Error getting name list", e);
@@ -49,3 +103,148 @@ pub fn delete(db: &DataSource, name: Option<String>) {
         }
     };
 }
+
+/// Deletes every series matching `pattern`, listing them first and asking
+/// unless `yes` is given.
+pub fn delete_matching(db: &DataSource, pattern: &str, yes: bool) -> i32 {
+    let names = match db.matching(pattern) {
+        Ok(n) => n,
+        Err(e) => {
+            do_error("Error getting series list", e);
+            return 1;
+        }
+    };
+
+    if names.is_empty() {
+        eprintln!("No series match '{}'", pattern);
+        return 1;
+    }
+
+    println!("'{}' matches {} series:", pattern, names.len());
+    for name in &names {
+        println!("  {}", name);
+    }
+
+    if !yes {
+        if !termion::is_tty(&io::stdin()) {
+            eprintln!("Not deleting without --yes");
+            return 1;
+        }
+
+        let answer: String = input_new().msg("Delete them all? [y/N] ").get();
+        if !["y", "yes"].contains(&answer.trim().to_lowercase().as_str()) {
+            println!("Nothing deleted");
+            return 0;
+        }
+    }
+
+    let mut code = 0;
+    for name in &names {
+        match db.delete_series(name) {
+            Ok(n) => println!("Deleted {}: {} measurements", name, n),
+            Err(e) => {
+                do_error(&format!("Unable to delete {}", name), e);
+                code = 1;
+            }
+        }
+    }
+
+    code
+}
+
+/// Updates the metadata of `name`. Fields set in `changes` are replaced,
+/// fields named in `unset` are cleared and the rest are kept.
+pub fn set(db: &DataSource, name: &str, changes: Meta, unset: &[String]) -> i32 {
+    let series = match db.get_series(name) {
+        Ok(Some(s)) => s,
+        Ok(None) => {
+            eprintln!("Series not found");
+            return 1;
+        },
+        Err(e) => {
+            do_error("Error querying series", e);
+            return 1;
+        }
+    };
+
+    let mut meta = series.meta;
+    for field in unset {
+        match field.as_str() {
+            "display-name" => meta.display_name = None,
+            "description" => meta.description = None,
+            "precision" => meta.precision = None,
+            "min" => meta.min = None,
+            "max" => meta.max = None,
+            "warn" => meta.warn = None,
+            "crit" => meta.crit = None,
+            "agg" => meta.aggregation = None,
+            "kind" => meta.kind = None,
+            f => {
+                eprintln!(
+                    "Unknown field '{}', expected display-name, description, precision, min, max, warn, crit, agg or kind",
+                    f
+                );
+                return 1;
+            }
+        }
+    }
+
+    meta.display_name = changes.display_name.or(meta.display_name);
+    meta.description = changes.description.or(meta.description);
+    meta.precision = changes.precision.or(meta.precision);
+    meta.min = changes.min.or(meta.min);
+    meta.max = changes.max.or(meta.max);
+    meta.warn = changes.warn.or(meta.warn);
+    meta.crit = changes.crit.or(meta.crit);
+    meta.aggregation = changes.aggregation.or(meta.aggregation);
+    meta.kind = changes.kind.or(meta.kind);
+
+    if let (Some(min), Some(max)) = (meta.min, meta.max) {
+        if min >= max {
+            eprintln!("--min should be below --max");
+            return 1;
+        }
+    }
+
+    if let Err(e) = db.set_meta(series.id, &meta) {
+        do_error("Error saving series metadata", e);
+        return 1;
+    }
+
+    show(db, name)
+}
+
+/// Prints the metadata of `name`.
+pub fn show(db: &DataSource, name: &str) -> i32 {
+    let series = match db.get_series(name) {
+        Ok(Some(s)) => s,
+        Ok(None) => {
+            eprintln!("Series not found");
+            return 1;
+        },
+        Err(e) => {
+            do_error("Error querying series", e);
+            return 1;
+        }
+    };
+
+    let meta = &series.meta;
+    let show = |v: Option<String>| v.unwrap_or_else(|| "-".into());
+
+    let mut table = Table::new();
+    table.add_row(row!["FIELD", "VALUE"]);
+    table.add_row(row!["name", series.name]);
+    table.add_row(row!["unit", series.unit]);
+    table.add_row(row!["display-name", show(meta.display_name.clone())]);
+    table.add_row(row!["description", show(meta.description.clone())]);
+    table.add_row(row!["precision", show(meta.precision.map(|p| p.to_string()))]);
+    table.add_row(row!["min", show(meta.min.map(|v| v.to_string()))]);
+    table.add_row(row!["max", show(meta.max.map(|v| v.to_string()))]);
+    table.add_row(row!["warn", show(meta.warn.map(|t| t.to_string()))]);
+    table.add_row(row!["crit", show(meta.crit.map(|t| t.to_string()))]);
+    table.add_row(row!["agg", show(meta.aggregation.map(|a| a.to_string()))]);
+    table.add_row(row!["kind", show(meta.kind.map(|k| k.to_string()))]);
+    table.printstd();
+
+    0
+}
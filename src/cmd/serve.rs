@@ -0,0 +1,59 @@
+use do_error;
+use api;
+use http::{self, Response};
+use prometheus;
+use data::DataSource;
+
+use std::net::TcpListener;
+
+fn bind(addr: &str) -> TcpListener {
+    match TcpListener::bind(addr) {
+        Ok(l) => l,
+        Err(e) => {
+            do_error(&format!("Unable to bind {}", addr), e);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn metrics(db: &DataSource, req: &http::Request) -> Response {
+    match (req.method.as_str(), req.path.as_str()) {
+        ("GET", "/metrics") => match prometheus::render(db, &[]) {
+            Ok(body) => Response::new(200, "text/plain; version=0.0.4; charset=utf-8", body),
+            Err(e) => {
+                error!("Error rendering metrics: {}", e);
+                Response::text(500, "Error rendering metrics\n")
+            }
+        },
+        (_, "/metrics") => Response::text(405, "Method not allowed\n"),
+        _ => Response::text(404, "Not found\n")
+    }
+}
+
+pub fn serve(db: &DataSource, listen: Option<String>, prometheus: Option<String>) {
+    if listen.is_none() && prometheus.is_none() {
+        eprintln!("Nothing to serve, use --listen and/or --prometheus");
+        std::process::exit(1);
+    }
+
+    // Listener 0 is the API (if any), the other is metrics only
+    let mut listeners = vec![];
+    if let Some(ref addr) = listen {
+        listeners.push(bind(addr));
+        info!("Serving API on {}", addr);
+    }
+    if let Some(ref addr) = prometheus {
+        listeners.push(bind(addr));
+        info!("Serving Prometheus metrics on {}", addr);
+    }
+
+    let api_listener = if listen.is_some() { Some(0) } else { None };
+
+    http::serve(listeners, |i, req| {
+        if Some(i) == api_listener {
+            api::handle(db, req)
+        } else {
+            metrics(db, req)
+        }
+    });
+}
@@ -0,0 +1,72 @@
+use do_error;
+use glob;
+use sparkline;
+use data::DataSource;
+
+/// Prints a sparkline of the last `points` values of every series matching
+/// `pattern` (or all of them), one series per line.
+pub fn spark(db: &DataSource, pattern: Option<String>, points: u8) -> i32 {
+    let list = match db.list_series() {
+        Ok(l) => l,
+        Err(e) => {
+            do_error("Error getting series list", e);
+            return 1;
+        }
+    };
+
+    let names = list.iter()
+        .map(|s| s.name.as_str())
+        .filter(|n| pattern.as_ref().map(|p| glob::matches(p, n)).unwrap_or(true))
+        .collect::<Vec<_>>();
+
+    if names.is_empty() {
+        println!("No matching series");
+        return 0;
+    }
+
+    let mut rows = vec![];
+    for name in names {
+        let data = match db.series(name, points) {
+            Ok(Some(d)) => d,
+            Ok(None) => continue,
+            Err(e) => {
+                do_error("Error querying series data", e);
+                return 1;
+            }
+        };
+
+        let values = data.measurements.iter().rev().map(|m| m.measurement).collect::<Vec<_>>();
+        let (last, previous) = match (values.last(), values.len()) {
+            (Some(l), n) if n > 1 => (*l, Some(values[n - 2])),
+            (Some(l), _) => (*l, None),
+            (None, _) => {
+                rows.push((name, "(no data)".to_string(), String::new()));
+                continue;
+            }
+        };
+
+        let arrow = match previous {
+            Some(p) if last > p => "↑",
+            Some(p) if last < p => "↓",
+            _ => "→"
+        };
+
+        let value = if data.unit.is_empty() {
+            data.meta.format(last)
+        } else {
+            format!("{} {}", data.meta.format(last), data.unit)
+        };
+
+        rows.push((name, sparkline::render(&values, points as usize), format!("{} {}", arrow, value)));
+    }
+
+    // Line up the columns, counting characters rather than bytes
+    let name_width = rows.iter().map(|r| r.0.chars().count()).max().unwrap_or(0);
+    let spark_width = rows.iter().map(|r| r.1.chars().count()).max().unwrap_or(0);
+
+    for (name, spark, value) in rows {
+        println!("{:<nw$}  {:<sw$}  {}", name, spark, value, nw = name_width, sw = spark_width);
+    }
+
+    0
+}
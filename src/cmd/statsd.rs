@@ -0,0 +1,221 @@
+use do_error;
+use data::DataSource;
+
+use std::collections::HashMap;
+use std::io;
+use std::net::UdpSocket;
+use std::time::{Duration, Instant};
+
+/// Aggregates StatsD metrics between flushes.
+#[derive(Default)]
+struct Aggregator {
+    counters: HashMap<String, f64>,
+    gauges: HashMap<String, (f64, bool)>,
+    timers: HashMap<String, Vec<f64>>,
+}
+
+impl Aggregator {
+    /// Parses a single `name:value|type[|@rate]` line and folds it into the
+    /// current interval.
+    fn ingest(&mut self, line: &str) -> Result<(), String> {
+        let colon = line.rfind(':').ok_or("missing ':'")?;
+        let (name, rest) = line.split_at(colon);
+        let mut fields = rest[1..].split('|');
+
+        let raw = fields.next().unwrap_or("");
+        let kind = fields.next().ok_or("missing metric type")?;
+        let rate = match fields.next() {
+            Some(r) if r.starts_with('@') => {
+                r[1..].parse::<f64>().map_err(|e| format!("bad sample rate: {}", e))?
+            },
+            _ => 1.0
+        };
+
+        if name.is_empty() {
+            return Err("empty metric name".into());
+        }
+
+        let value = raw.parse::<f64>().map_err(|e| format!("bad value: {}", e))?;
+
+        match kind {
+            "c" => {
+                let rate = if rate > 0.0 { rate } else { 1.0 };
+                *self.counters.entry(name.into()).or_insert(0.0) += value / rate;
+            },
+            "g" => {
+                let gauge = self.gauges.entry(name.into()).or_insert((0.0, false));
+                if raw.starts_with('+') || raw.starts_with('-') {
+                    gauge.0 += value;
+                } else {
+                    gauge.0 = value;
+                }
+                gauge.1 = true;
+            },
+            "ms" | "h" => {
+                self.timers.entry(name.into()).or_default().push(value);
+            },
+            t => return Err(format!("unsupported metric type '{}'", t))
+        }
+
+        Ok(())
+    }
+
+    /// Drains the current interval into `(series, value)` pairs.
+    ///
+    /// Gauges keep their last value so relative updates work across flushes,
+    /// but are only written when they were touched in this interval.
+    fn flush(&mut self) -> Vec<(String, f64)> {
+        let mut out: Vec<(String, f64)> = self.counters.drain().collect();
+
+        for (name, gauge) in self.gauges.iter_mut() {
+            if gauge.1 {
+                out.push((name.clone(), gauge.0));
+                gauge.1 = false;
+            }
+        }
+
+        for (name, values) in self.timers.drain() {
+            if values.is_empty() { continue; }
+
+            let count = values.len() as f64;
+            let lower = values.iter().cloned().fold(f64::INFINITY, f64::min);
+            let upper = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+            let mean = values.iter().sum::<f64>() / count;
+
+            out.push((format!("{}.mean", name), mean));
+            out.push((format!("{}.lower", name), lower));
+            out.push((format!("{}.upper", name), upper));
+            out.push((format!("{}.count", name), count));
+        }
+
+        out
+    }
+}
+
+pub fn listen(db: &DataSource, addr: &str, flush: u64, create: bool) {
+    let socket = match UdpSocket::bind(addr) {
+        Ok(s) => s,
+        Err(e) => {
+            do_error(&format!("Unable to bind {}", addr), e);
+            std::process::exit(1);
+        }
+    };
+
+    info!("StatsD listener on {}, flushing every {}s", addr, flush);
+
+    let interval = Duration::from_secs(flush.max(1));
+    let mut next_flush = Instant::now() + interval;
+    let mut agg = Aggregator::default();
+    let mut buf = [0u8; 65535];
+
+    loop {
+        let now = Instant::now();
+        if now >= next_flush {
+            for (series, value) in agg.flush() {
+                match db.unwritable(&series, create) {
+                    Ok(Some(reason)) => warn!("Skipping StatsD metric: {}", reason),
+                    Ok(None) => {
+                        if let Err(e) = db.measure(&series, value, create) {
+                            do_error(&format!("Error adding measurement to {}", series), e);
+                        }
+                    },
+                    Err(e) => do_error(&format!("Error looking up {}", series), e)
+                }
+            }
+            next_flush = now + interval;
+            continue;
+        }
+
+        // Wake up in time for the next flush even if nothing arrives
+        socket.set_read_timeout(Some(next_flush - now)).unwrap();
+
+        let len = match socket.recv_from(&mut buf) {
+            Ok((len, _)) => len,
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock
+                || e.kind() == io::ErrorKind::TimedOut => continue,
+            Err(e) => {
+                do_error("Error reading from socket", e);
+                std::process::exit(1);
+            }
+        };
+
+        String::from_utf8_lossy(&buf[..len])
+            .lines()
+            .map(|l| l.trim())
+            .filter(|l| !l.is_empty())
+            .for_each(|line| {
+                if let Err(e) = agg.ingest(line) {
+                    warn!("Invalid StatsD metric '{}': {}", line, e);
+                }
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flushed(agg: &mut Aggregator) -> Vec<(String, f64)> {
+        let mut out = agg.flush();
+        out.sort_by(|a, b| a.0.cmp(&b.0));
+        out
+    }
+
+    #[test]
+    fn counters_sum_and_scale_by_rate() {
+        let mut agg = Aggregator::default();
+        agg.ingest("hits:1|c").unwrap();
+        agg.ingest("hits:2|c|@0.5").unwrap();
+
+        assert_eq!(vec![("hits".to_string(), 5.0)], flushed(&mut agg));
+        assert!(agg.flush().is_empty());
+    }
+
+    #[test]
+    fn gauges_keep_their_value_across_flushes() {
+        let mut agg = Aggregator::default();
+        agg.ingest("temp:20|g").unwrap();
+        agg.ingest("temp:+5|g").unwrap();
+        assert_eq!(vec![("temp".to_string(), 25.0)], flushed(&mut agg));
+
+        // Untouched gauges aren't written again
+        assert!(agg.flush().is_empty());
+
+        agg.ingest("temp:-10|g").unwrap();
+        assert_eq!(vec![("temp".to_string(), 15.0)], flushed(&mut agg));
+    }
+
+    #[test]
+    fn timers_summarise() {
+        let mut agg = Aggregator::default();
+        for v in &["10", "20", "30"] {
+            agg.ingest(&format!("req:{}|ms", v)).unwrap();
+        }
+
+        let expected = vec![
+            ("req.count".to_string(), 3.0),
+            ("req.lower".to_string(), 10.0),
+            ("req.mean".to_string(), 20.0),
+            ("req.upper".to_string(), 30.0)
+        ];
+        assert_eq!(expected, flushed(&mut agg));
+    }
+
+    #[test]
+    fn names_may_contain_colons() {
+        let mut agg = Aggregator::default();
+        agg.ingest("job:rate:1|c").unwrap();
+        assert_eq!(vec![("job:rate".to_string(), 1.0)], flushed(&mut agg));
+    }
+
+    #[test]
+    fn rejects_malformed_lines() {
+        let mut agg = Aggregator::default();
+        assert!(agg.ingest("hits").is_err());
+        assert!(agg.ingest("hits:1").is_err());
+        assert!(agg.ingest(":1|c").is_err());
+        assert!(agg.ingest("hits:x|c").is_err());
+        assert!(agg.ingest("hits:1|s").is_err());
+        assert!(agg.ingest("hits:1|c|@x").is_err());
+    }
+}
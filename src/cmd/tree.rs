@@ -0,0 +1,34 @@
+use do_error;
+use data::DataSource;
+use tree::Node;
+
+/// Prints the series names below `prefix` (or all of them) as a tree.
+pub fn tree(db: &DataSource, prefix: Option<String>, depth: Option<usize>) -> i32 {
+    let list = match db.list_series() {
+        Ok(l) => l,
+        Err(e) => {
+            do_error("Error getting series list", e);
+            return 1;
+        }
+    };
+
+    let root = Node::build(list.iter().map(|s| s.name.as_str()));
+    let node = match prefix {
+        Some(ref p) => root.find(p),
+        None => Some(&root)
+    };
+
+    match node {
+        Some(node) if node.count() > 0 => {
+            if !node.path.is_empty() {
+                println!("{} ({})", node.path, node.count());
+            }
+            for line in node.lines(depth) {
+                println!("{}", line);
+            }
+        },
+        _ => println!("No matching series")
+    }
+
+    0
+}
@@ -0,0 +1,219 @@
+//! Settings from the config file, `~/.config/trk/config.toml`.
+//!
+//! Everything is optional. Flags (and their `TRK_*` environment variables)
+//! win over the file, which wins over the built-in defaults.
+
+use cli::{ColorMode, ExportFormat, PlotStyle};
+use duration;
+use stats::Aggregation;
+use textplots::Color;
+
+use log::LevelFilter;
+
+use serde::de::{self, Deserialize, Deserializer};
+use toml;
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+#[derive(Default, Deserialize)]
+pub struct Settings {
+    /// Path to store data, `~` is expanded
+    pub data_root: Option<PathBuf>,
+    /// Source name
+    pub file: Option<PathBuf>,
+    /// Where to log, `~` is expanded
+    pub log_file: Option<PathBuf>,
+    #[serde(default, deserialize_with = "parsed")]
+    pub log_level: Option<LevelFilter>,
+    #[serde(default, deserialize_with = "parsed")]
+    pub color: Option<ColorMode>,
+    #[serde(default)]
+    pub plot: PlotDefaults,
+    #[serde(default)]
+    pub export: ExportDefaults,
+    /// Display settings by series name
+    #[serde(default)]
+    pub series: BTreeMap<String, SeriesDisplay>,
+    #[serde(default)]
+    pub dashboards: BTreeMap<String, Dashboard>
+}
+
+/// Defaults for `trk plot`.
+#[derive(Default, Deserialize)]
+pub struct PlotDefaults {
+    #[serde(default, deserialize_with = "parsed")]
+    pub style: Option<PlotStyle>,
+    pub points: Option<u8>,
+    #[serde(default)]
+    pub grid: bool,
+    pub width: Option<u32>,
+    pub height: Option<u32>
+}
+
+/// Defaults for `trk export`.
+#[derive(Default, Deserialize)]
+pub struct ExportDefaults {
+    #[serde(default, deserialize_with = "parsed")]
+    pub format: Option<ExportFormat>
+}
+
+/// How to show one series, over the plot defaults.
+#[derive(Clone, Copy, Default, Deserialize)]
+pub struct SeriesDisplay {
+    #[serde(default, deserialize_with = "parsed")]
+    pub style: Option<PlotStyle>,
+    /// Line colour
+    #[serde(default, deserialize_with = "parsed")]
+    pub color: Option<Color>,
+    /// Fixed Y-axis ends
+    pub ymin: Option<f32>,
+    pub ymax: Option<f32>
+}
+
+impl Settings {
+    /// Display settings for `series`, falling back to the plot defaults.
+    pub fn display(&self, series: &str) -> SeriesDisplay {
+        let mut display = self.series.get(series).cloned().unwrap_or_default();
+        display.style = display.style.or(self.plot.style);
+        display
+    }
+}
+
+/// A named set of charts shown together by `trk dash`.
+#[derive(Deserialize)]
+pub struct Dashboard {
+    /// Charts per row
+    #[serde(default = "default_columns")]
+    pub columns: usize,
+    /// Chart height in braille dots (4 per row)
+    #[serde(default = "default_height")]
+    pub height: u32,
+    pub panels: Vec<Panel>
+}
+
+#[derive(Deserialize)]
+pub struct Panel {
+    pub series: String,
+    /// Shown above the chart instead of the series name
+    pub title: Option<String>,
+    #[serde(default, deserialize_with = "parsed")]
+    pub style: Option<PlotStyle>,
+    /// Only points from this long ago on (eg 24h), rather than the last
+    /// `points` of them
+    #[serde(default, deserialize_with = "period")]
+    pub range: Option<u32>,
+    #[serde(default = "default_points")]
+    pub points: u8,
+    /// Combine points into buckets this long (eg 1h)
+    #[serde(default, deserialize_with = "period")]
+    pub bucket: Option<u32>,
+    /// How to combine the points in a bucket, also shown for the whole panel
+    #[serde(default, deserialize_with = "parsed")]
+    pub aggregation: Option<Aggregation>
+}
+
+fn default_columns() -> usize { 2 }
+fn default_height() -> u32 { 40 }
+fn default_points() -> u8 { 50 }
+
+/// Reads an optional string through `FromStr`, the same as the CLI flags.
+fn parsed<'de, D, T>(d: D) -> Result<Option<T>, D::Error>
+    where D: Deserializer<'de>, T: FromStr, T::Err: fmt::Display
+{
+    match Option::<String>::deserialize(d)? {
+        Some(s) => s.parse().map(Some).map_err(de::Error::custom),
+        None => Ok(None)
+    }
+}
+
+/// Reads an optional duration like `24h`, in seconds.
+fn period<'de, D>(d: D) -> Result<Option<u32>, D::Error>
+    where D: Deserializer<'de>
+{
+    match Option::<String>::deserialize(d)? {
+        Some(s) => duration::parse(&s).map(Some).map_err(de::Error::custom),
+        None => Ok(None)
+    }
+}
+
+/// Expands a leading `~` to `$HOME`.
+pub fn expand(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), env::var_os("HOME")) {
+        (Ok(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => path.to_path_buf()
+    }
+}
+
+/// Where the config file lives by default, under `$XDG_CONFIG_HOME` or
+/// `~/.config`.
+pub fn path() -> Option<PathBuf> {
+    let base = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".config")
+    };
+
+    Some(base.join("trk").join("config.toml"))
+}
+
+/// Loads the config file at `explicit`, or the default one if it exists.
+pub fn load(explicit: Option<PathBuf>) -> Result<Settings, String> {
+    let required = explicit.is_some();
+    let path = match explicit.or_else(path) {
+        Some(p) => p,
+        None => return Ok(Settings::default())
+    };
+
+    let text = match fs::read_to_string(&path) {
+        Ok(t) => t,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound && !required => return Ok(Settings::default()),
+        Err(e) => return Err(format!("{}: {}", path.display(), e))
+    };
+
+    toml::from_str(&text).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_paths_and_plot_defaults() {
+        let settings: Settings = toml::from_str(r#"
+            data_root = "/srv/metrics"
+            file = "work"
+            log_file = "/var/log/trk.log"
+
+            [plot]
+            style = "step"
+            grid = true
+
+            [series."inv.req.time"]
+            ymin = 0
+        "#).unwrap();
+
+        assert_eq!(Some(PathBuf::from("/srv/metrics")), settings.data_root);
+        assert_eq!(Some(PathBuf::from("work")), settings.file);
+        assert_eq!(Some(PathBuf::from("/var/log/trk.log")), settings.log_file);
+        assert!(settings.plot.grid);
+        assert_eq!(Some(0.0), settings.display("inv.req.time").ymin);
+    }
+
+    #[test]
+    fn rejects_bad_values() {
+        assert!(toml::from_str::<Settings>("[plot]\nstyle = \"wavy\"").is_err());
+    }
+
+    #[test]
+    fn expands_home() {
+        if let Some(home) = env::var_os("HOME") {
+            assert_eq!(PathBuf::from(&home).join("metrics"), expand(Path::new("~/metrics")));
+        }
+        assert_eq!(PathBuf::from("/tmp/~"), expand(Path::new("/tmp/~")));
+    }
+}
@@ -1,17 +1,77 @@
+use expr::{self, Expr};
+use glob;
+use meta::Meta;
+
 use rusqlite::types::ToSql;
-use rusqlite::{Connection, NO_PARAMS};
+use rusqlite::{Connection, Row, NO_PARAMS};
 
+use std::fs::{self, File};
+use std::io::{self, Read};
 use std::path::Path;
+use std::str::FromStr;
 
 type Result<T> = ::std::result::Result<T, rusqlite::Error>;
 
+const SELECT_SERIES: &str =
+    "select s.id, s.name, s.unit, e.expression, e.bucket,
+            m.display_name, m.description, m.precision, m.min, m.max,
+            m.warn, m.crit, m.aggregation, m.kind
+     from series s
+     left join expression e on e.series = s.id
+     left join meta m on m.series = s.id";
+
 pub struct Series {
     pub id: i32,
     pub name: String,
     pub unit: String,
+    /// Set for virtual series, which are computed from others on read
+    pub expression: Option<Expression>,
+    pub meta: Meta,
     pub measurements: Vec<Measurement>
 }
 
+impl Series {
+    /// The display name if there is one, otherwise the name.
+    pub fn title(&self) -> &str {
+        self.meta.display_name.as_ref().unwrap_or(&self.name)
+    }
+}
+
+pub struct Expression {
+    pub expr: String,
+    /// Seconds to align input points to
+    pub bucket: u32
+}
+
+/// A column stored as text, `None` if it's empty or no longer parses.
+fn parsed<T: FromStr>(row: &Row, column: usize) -> Option<T> {
+    row.get::<_, Option<String>>(column).and_then(|v| v.parse().ok())
+}
+
+fn series_from_row(row: &Row) -> Series {
+    let expression: Option<String> = row.get(3);
+    let precision: Option<u32> = row.get(7);
+
+    Series {
+        id: row.get(0),
+        name: row.get(1),
+        unit: row.get(2),
+        expression: expression.map(|expr| Expression { expr, bucket: row.get(4) }),
+        meta: Meta {
+            display_name: row.get(5),
+            description: row.get(6),
+            precision: precision.map(|p| p as usize),
+            min: row.get(8),
+            max: row.get(9),
+            warn: parsed(row, 10),
+            crit: parsed(row, 11),
+            aggregation: parsed(row, 12),
+            kind: parsed(row, 13)
+        },
+        measurements: vec![]
+    }
+}
+
 pub struct Measurement {
     pub timestamp: u32,
     pub run: u32,
@@ -45,6 +105,39 @@ impl DataSource {
               foreign key(series) references series(id)
             );", NO_PARAMS
         )?;
+        conn.execute(
+            "create table if not exists tag (
+              series integer,
+              run integer,
+              key text,
+              value text,
+              primary key(series, run, key),
+              foreign key(series, run) references measurement(series, run)
+            );", NO_PARAMS
+        )?;
+        conn.execute(
+            "create table if not exists expression (
+              series integer primary key,
+              expression text,
+              bucket integer,
+              foreign key(series) references series(id)
+            );", NO_PARAMS
+        )?;
+        conn.execute(
+            "create table if not exists meta (
+              series integer primary key,
+              display_name text,
+              description text,
+              precision integer,
+              min real,
+              max real,
+              warn text,
+              crit text,
+              aggregation text,
+              kind text,
+              foreign key(series) references series(id)
+            );", NO_PARAMS
+        )?;
 
         Ok(DataSource { conn })
     }
@@ -55,15 +148,53 @@ impl DataSource {
             None => { return Ok(None); }
         };
 
+        if series.expression.is_some() {
+            let mut data = self.evaluate(&series, |name| self.series(name, points))?;
+            data.measurements.truncate(points as usize);
+            return Ok(Some(data));
+        }
+
         let mut sth = self.conn.prepare(
             "select timestamp, run, measurement
              from measurement
              where series = ?1
-             order by timestamp desc limit ?2"
+             order by timestamp desc, run desc limit ?2"
+        )?;
+
+        let result = sth.query_map(
+            &[&series.id as &dyn ToSql, &points],
+            |row| Measurement {
+                timestamp: row.get(0),
+                run: row.get(1),
+                measurement: row.get(2)
+            })?;
+
+        result
+            .for_each(|m| series.measurements.push(m.unwrap()));
+
+        Ok(Some(series))
+    }
+
+    /// Measurements with `from <= timestamp <= to`, newest first.
+    pub fn range(&self, series: &str, from: u32, to: u32) -> Result<Option<Series>> {
+        let mut series = match self.get_series(series)? {
+            Some(s) => s,
+            None => { return Ok(None); }
+        };
+
+        if series.expression.is_some() {
+            return self.evaluate(&series, |name| self.range(name, from, to)).map(Some);
+        }
+
+        let mut sth = self.conn.prepare(
+            "select timestamp, run, measurement
+             from measurement
+             where series = ?1 and timestamp between ?2 and ?3
+             order by timestamp desc, run desc"
         )?;
 
         let result = sth.query_map(
-            &[&series.id as &ToSql, &points],
+            &[&series.id as &dyn ToSql, &from, &to],
             |row| Measurement {
                 timestamp: row.get(0),
                 run: row.get(1),
@@ -76,7 +207,101 @@ impl DataSource {
         Ok(Some(series))
     }
 
+    /// Evaluates an ad-hoc expression over the last `points` of each input.
+    pub fn derive(&self, expression: &str, bucket: u32, points: u8) -> Result<Series> {
+        let series = Series {
+            id: 0,
+            name: expression.into(),
+            unit: String::new(),
+            expression: Some(Expression { expr: expression.into(), bucket }),
+            meta: Meta::default(),
+            measurements: vec![]
+        };
+
+        let mut data = self.evaluate(&series, |name| self.series(name, points))?;
+        data.measurements.truncate(points as usize);
+        Ok(data)
+    }
+
+    /// Computes a virtual series from its inputs, fetched with `fetch`.
+    /// Missing inputs just mean no data.
+    fn evaluate<F>(&self, series: &Series, fetch: F) -> Result<Series>
+        where F: Fn(&str) -> Result<Option<Series>>
+    {
+        let mut data = self.evaluate_inputs(series, fetch)?;
+        data.meta = series.meta.clone();
+        Ok(data)
+    }
+
+    fn evaluate_inputs<F>(&self, series: &Series, fetch: F) -> Result<Series>
+        where F: Fn(&str) -> Result<Option<Series>>
+    {
+        let def = series.expression.as_ref().unwrap();
+        let parsed = match Expr::parse(&def.expr) {
+            Ok(e) => e,
+            Err(e) => {
+                warn!("Invalid expression for {}: {}", series.name, e);
+                return Ok(expr::evaluate(&Expr::Number(0.0), &series.name, &series.unit, def.bucket, &[]));
+            }
+        };
+
+        let mut inputs = vec![];
+        for name in parsed.series() {
+            match fetch(&name)? {
+                Some(s) => inputs.push(s),
+                None => {
+                    warn!("Series {} used by {} not found", name, series.name);
+                    return Ok(expr::evaluate(&parsed, &series.name, &series.unit, def.bucket, &[]));
+                }
+            }
+        }
+
+        Ok(expr::evaluate(&parsed, &series.name, &series.unit, def.bucket, &inputs))
+    }
+
     pub fn measure(&self, series: &str, value: f64, create: bool) -> Result<usize> {
+        self.measure_at(series, value, None, &[], create)
+    }
+
+    /// Why `series` can't be measured, if it can't: it doesn't exist (and
+    /// `create` isn't set) or it's virtual. `measure` exits in those cases,
+    /// so listeners check first and skip the point instead.
+    pub fn unwritable(&self, series: &str, create: bool) -> Result<Option<String>> {
+        let reason = match self.get_series(series)? {
+            Some(ref s) if s.expression.is_some() => Some(format!("{} is a virtual series", series)),
+            None if !create => Some(format!("series {} not found, use -c to create it", series)),
+            _ => None
+        };
+
+        Ok(reason)
+    }
+
+    /// Run `f` in a single transaction, so either everything it writes is
+    /// kept or none of it is.
+    pub fn transaction<T, F: FnOnce() -> Result<T>>(&self, f: F) -> Result<T> {
+        self.conn.execute_batch("begin")?;
+        match f() {
+            Ok(v) => {
+                self.conn.execute_batch("commit")?;
+                Ok(v)
+            },
+            Err(e) => {
+                self.conn.execute_batch("rollback")?;
+                Err(e)
+            }
+        }
+    }
+
+    /// Like `measure` but with an optional explicit unix timestamp (instead of
+    /// now) and a set of key/value tags attached to the measurement.
+    pub fn measure_at(
+        &self,
+        series: &str,
+        value: f64,
+        timestamp: Option<u32>,
+        tags: &[(String, String)],
+        create: bool
+    ) -> Result<usize> {
         let series = match self.get_series(series)? {
             Some(s) => s,
             None => {
@@ -89,37 +314,84 @@ impl DataSource {
             }
         };
 
-        self.conn.execute(
+        if series.expression.is_some() {
+            eprintln!("{} is a virtual series and can't be measured directly", series.name);
+            ::std::process::exit(1);
+        }
+
+        let rows = self.conn.execute(
             "insert into measurement values (
               ?1,
-              strftime('%s','now'),
+              coalesce(?4, strftime('%s','now')),
               (select coalesce(max(run), 0) from measurement where series = ?2) + 1,
               ?3
             )",
-            &[&series.id as &ToSql, &series.id, &value]
-        )
+            &[&series.id as &dyn ToSql, &series.id, &value, &timestamp]
+        )?;
+
+        if !tags.is_empty() {
+            let rowid = self.conn.last_insert_rowid();
+            let mut sth = self.conn.prepare(
+                "insert or replace into tag
+                 select series, run, ?2, ?3 from measurement where rowid = ?1"
+            )?;
+
+            for (key, value) in tags {
+                sth.execute(&[&rowid as &dyn ToSql, key, value])?;
+            }
+        }
+
+        Ok(rows)
     }
 
     pub fn create_series(&self, name: &str, unit: &str) -> Result<Series> {
         let mut ins = self.conn.prepare("insert into series (name, unit) values (?1, ?2)")?;
-        let series_id = ins.insert(&[&name as &ToSql, &unit])?;
+        let series_id = ins.insert(&[&name as &dyn ToSql, &unit])?;
         let series_id = series_id as u32;
 
-        let mut sth = self.conn.prepare("select id, name, unit from series where id = ?1")?;
+        let mut sth = self.conn.prepare(&format!("{} where s.id = ?1", SELECT_SERIES))?;
 
         sth.query_row(
-            &[&series_id as &ToSql],
-            |row| Ok(
-                Series {
-                    id: row.get(0),
-                    name: row.get(1),
-                    unit: row.get(2),
-                    measurements: vec![]
-                }
-            )
+            &[&series_id as &dyn ToSql],
+            |row| Ok(series_from_row(row))
         )?
     }
 
+    /// Creates a virtual series computed from `expression` over other series.
+    pub fn create_virtual_series(&self, name: &str, unit: &str, expression: &str, bucket: u32) -> Result<Series> {
+        let mut series = self.create_series(name, unit)?;
+
+        self.conn.execute(
+            "insert into expression (series, expression, bucket) values (?1, ?2, ?3)",
+            &[&series.id as &dyn ToSql, &expression, &bucket]
+        )?;
+
+        series.expression = Some(Expression { expr: expression.into(), bucket });
+        Ok(series)
+    }
+
+    /// Replaces the metadata of a series.
+    pub fn set_meta(&self, series: i32, meta: &Meta) -> Result<()> {
+        // Thresholds and the like are stored as they're written on the command line
+        let precision = meta.precision.map(|p| p as u32);
+        let warn = meta.warn.map(|t| t.to_string());
+        let crit = meta.crit.map(|t| t.to_string());
+        let aggregation = meta.aggregation.map(|a| a.to_string());
+        let kind = meta.kind.map(|k| k.to_string());
+
+        self.conn.execute(
+            "insert or replace into meta
+               (series, display_name, description, precision, min, max, warn, crit, aggregation, kind)
+             values (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            &[
+                &series as &dyn ToSql, &meta.display_name, &meta.description, &precision,
+                &meta.min, &meta.max, &warn, &crit, &aggregation, &kind
+            ]
+        )?;
+
+        Ok(())
+    }
+
     pub fn delete_series(&self, series: &str) -> Result<usize> {
         let series = match self.get_series(series)? {
             Some(s) => s,
@@ -129,52 +401,85 @@ impl DataSource {
             }
         };
 
+        self.conn.execute(
+            "delete from tag where series = ?1",
+            &[&series.id as &dyn ToSql]
+        )?;
+
+        self.conn.execute(
+            "delete from expression where series = ?1",
+            &[&series.id as &dyn ToSql]
+        )?;
+
+        self.conn.execute(
+            "delete from meta where series = ?1",
+            &[&series.id as &dyn ToSql]
+        )?;
+
         let measurements = self.conn.execute(
             "delete from measurement where series = ?1",
-            &[&series.id as &ToSql]
+            &[&series.id as &dyn ToSql]
         )?;
 
         self.conn.execute(
             "delete from series where id = ?1",
-            &[&series.id as &ToSql]
+            &[&series.id as &dyn ToSql]
         )?;
 
         Ok(measurements)
     }
 
     pub fn list_series(&self) -> Result<Vec<Series>> {
-        let mut sth = self.conn.prepare("select id, name, unit from series")?;
-
-        let list = sth.query_map(
-            NO_PARAMS,
-            |row| {
-                Series {
-                    id: row.get(0),
-                    name: row.get(1),
-                    unit: row.get(2),
-                    measurements: vec![]
-                }
-            })?
+        let mut sth = self.conn.prepare(SELECT_SERIES)?;
+
+        let list = sth.query_map(NO_PARAMS, series_from_row)?
             .map(|s| s.unwrap())
             .collect::<Vec<_>>();
 
         Ok(list)
     }
 
-    fn get_series(&self, series: &str) -> Result<Option<Series>> {
+    /// Names of the series matching `pattern`, where `*` matches anything
+    /// (eg `inv.*` or `*.time`).
+    pub fn matching(&self, pattern: &str) -> Result<Vec<String>> {
+        let names = self.list_series()?
+            .into_iter()
+            .map(|s| s.name)
+            .filter(|n| glob::matches(pattern, n))
+            .collect();
+
+        Ok(names)
+    }
+
+    /// How many measurements a series has and when the last was taken.
+    pub fn summary(&self, series: i32) -> Result<(u32, Option<u32>)> {
+        self.conn.query_row(
+            "select count(*), max(timestamp) from measurement where series = ?1",
+            &[&series as &dyn ToSql],
+            |row| (row.get(0), row.get(1))
+        )
+    }
+
+    /// Tags attached to a single measurement.
+    pub fn tags(&self, series: i32, run: u32) -> Result<Vec<(String, String)>> {
         let mut sth = self.conn.prepare(
-            "select * from series where name = ?1",
+            "select key, value from tag where series = ?1 and run = ?2 order by key"
         )?;
 
-        let result = sth.query_row(
-            &[&series as &ToSql],
-            |row| Series {
-                id: row.get(0),
-                name: row.get(1),
-                unit: row.get(2),
-                measurements: vec![]
-            }
-        );
+        let tags = sth.query_map(
+            &[&series as &dyn ToSql, &run],
+            |row| (row.get(0), row.get(1))
+        )?
+            .map(|t| t.unwrap())
+            .collect::<Vec<_>>();
+
+        Ok(tags)
+    }
+
+    pub fn get_series(&self, series: &str) -> Result<Option<Series>> {
+        let mut sth = self.conn.prepare(&format!("{} where s.name = ?1", SELECT_SERIES))?;
+
+        let result = sth.query_row(&[&series as &dyn ToSql], series_from_row);
 
         // trick to get our a Result<Option<T>>
         match result {
@@ -183,3 +488,76 @@ impl DataSource {
         }
     }
 }
+
+/// Names of the data files in `data_path` (what `--data-file` takes), found
+/// by their SQLite header so the log file and anything else is skipped.
+pub fn files(data_path: &Path) -> io::Result<Vec<String>> {
+    let mut files = vec![];
+
+    for entry in fs::read_dir(data_path)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_file() {
+            continue;
+        }
+
+        let mut header = [0u8; 16];
+        let is_db = File::open(entry.path())
+            .and_then(|mut f| f.read_exact(&mut header))
+            .map(|_| &header == b"SQLite format 3\0")
+            .unwrap_or(false);
+
+        if is_db {
+            files.push(entry.file_name().to_string_lossy().into_owned());
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    use std::env;
+    use std::process;
+
+    /// An empty data file in a temporary directory, for tests.
+    pub fn scratch(name: &str) -> DataSource {
+        let dir = env::temp_dir().join(format!("trk-test-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let file = dir.join(name);
+        let _ = fs::remove_file(&file);
+        DataSource::new(&dir, &file).unwrap()
+    }
+
+    #[test]
+    fn finds_data_files_by_header() {
+        let db = scratch("files-db");
+        db.measure("a", 1.0, true).unwrap();
+
+        let dir = env::temp_dir().join(format!("trk-test-{}", process::id()));
+        fs::write(dir.join("trk.log"), "not a database").unwrap();
+
+        let found = files(&dir).unwrap();
+        assert!(found.contains(&"files-db".to_string()));
+        assert!(!found.contains(&"trk.log".to_string()));
+    }
+
+    #[test]
+    fn transactions_roll_back_on_error() {
+        let db = scratch("transaction-db");
+        db.measure("a", 1.0, true).unwrap();
+
+        let failed: Result<()> = db.transaction(|| {
+            db.measure("a", 2.0, false)?;
+            Err(rusqlite::Error::QueryReturnedNoRows)
+        });
+        assert!(failed.is_err());
+        assert_eq!(1, db.series("a", 10).unwrap().unwrap().measurements.len());
+
+        db.transaction(|| db.measure("a", 3.0, false)).unwrap();
+        assert_eq!(2, db.series("a", 10).unwrap().unwrap().measurements.len());
+    }
+}
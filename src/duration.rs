@@ -0,0 +1,66 @@
+//! Short human durations like `90s`, `5m`, `12h`, `7d` or `2w`.
+
+/// Parses a duration into seconds. A bare number is taken as seconds.
+pub fn parse(input: &str) -> Result<u32, String> {
+    let input = input.trim();
+    let split = input.find(|c: char| !c.is_ascii_digit()).unwrap_or(input.len());
+    let (number, unit) = input.split_at(split);
+
+    let number = number.parse::<u32>()
+        .map_err(|_| format!("invalid duration '{}', expected eg 30s, 5m, 12h, 7d", input))?;
+
+    let scale = match unit {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86_400,
+        "w" => 7 * 86_400,
+        u => return Err(format!("unknown duration unit '{}', expected s, m, h, d or w", u))
+    };
+
+    number.checked_mul(scale).ok_or_else(|| format!("duration '{}' is too long", input))
+}
+
+/// Formats seconds using the largest unit that divides it evenly.
+pub fn format(secs: u32) -> String {
+    let units = [(7 * 86_400, "w"), (86_400, "d"), (3600, "h"), (60, "m")];
+
+    for &(scale, unit) in &units {
+        if secs > 0 && secs % scale == 0 {
+            return format!("{}{}", secs / scale, unit);
+        }
+    }
+
+    format!("{}s", secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_units() {
+        assert_eq!(Ok(90), parse("90"));
+        assert_eq!(Ok(90), parse("90s"));
+        assert_eq!(Ok(300), parse("5m"));
+        assert_eq!(Ok(43_200), parse("12h"));
+        assert_eq!(Ok(604_800), parse("7d"));
+        assert_eq!(Ok(1_209_600), parse("2w"));
+    }
+
+    #[test]
+    fn rejects_bad_durations() {
+        assert!(parse("").is_err());
+        assert!(parse("5y").is_err());
+        assert!(parse("-5m").is_err());
+        assert!(parse("99999999w").is_err());
+    }
+
+    #[test]
+    fn formats_with_the_largest_even_unit() {
+        assert_eq!("2w", format(1_209_600));
+        assert_eq!("36h", format(129_600));
+        assert_eq!("90s", format(90));
+        assert_eq!("0s", format(0));
+    }
+}
@@ -0,0 +1,392 @@
+//! Arithmetic expressions over series, for derived and virtual series.
+//!
+//! `errors / requests * 100`, `avg(web1.load, web2.load)`,
+//! `clamp(free / total, 0, 1)`. Series names containing characters other
+//! than letters, digits, `_`, `.` and `:` can be double quoted.
+
+use data::{Measurement, Series};
+use meta::Meta;
+
+use std::collections::BTreeMap;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Op { Add, Sub, Mul, Div }
+
+pub enum Expr {
+    Number(f64),
+    Series(String),
+    Neg(Box<Expr>),
+    Binary(Op, Box<Expr>, Box<Expr>),
+    Call(String, Vec<Expr>)
+}
+
+#[derive(PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    Op(Op),
+    Open,
+    Close,
+    Comma
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = vec![];
+    let mut chars = input.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            ' ' | '\t' | '\n' => { chars.next(); },
+            '+' => { chars.next(); tokens.push(Token::Op(Op::Add)); },
+            '-' => { chars.next(); tokens.push(Token::Op(Op::Sub)); },
+            '*' => { chars.next(); tokens.push(Token::Op(Op::Mul)); },
+            '/' => { chars.next(); tokens.push(Token::Op(Op::Div)); },
+            '(' => { chars.next(); tokens.push(Token::Open); },
+            ')' => { chars.next(); tokens.push(Token::Close); },
+            ',' => { chars.next(); tokens.push(Token::Comma); },
+            '"' => {
+                chars.next();
+                let name: String = chars.by_ref().take_while(|c| *c != '"').collect();
+                tokens.push(Token::Ident(name));
+            },
+            c if c.is_ascii_digit() || c == '.' => {
+                let mut number = String::new();
+                let mut exponent = false;
+                while let Some(&c) = chars.peek() {
+                    let signed = (c == '+' || c == '-') && number.ends_with(['e', 'E']);
+                    if c.is_ascii_digit() || (c == '.' && !exponent) || signed {
+                        number.push(c);
+                        chars.next();
+                    } else if (c == 'e' || c == 'E') && !exponent {
+                        exponent = true;
+                        number.push(c);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                let n = number.parse::<f64>().map_err(|_| format!("invalid number '{}'", number))?;
+                tokens.push(Token::Number(n));
+            },
+            c if c.is_alphabetic() || c == '_' => {
+                let mut name = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_alphanumeric() || c == '_' || c == '.' || c == ':' {
+                        name.push(c);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                tokens.push(Token::Ident(name));
+            },
+            c => return Err(format!("unexpected '{}'", c))
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Recursive descent over the usual precedence: `+ -` < `* /` < unary `-`.
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<&Token> {
+        self.pos += 1;
+        self.tokens.get(self.pos - 1)
+    }
+
+    fn expect(&mut self, token: Token, what: &str) -> Result<(), String> {
+        if self.peek() == Some(&token) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(format!("expected {}", what))
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr, String> {
+        let mut lhs = self.term()?;
+        while let Some(&Token::Op(op)) = self.peek() {
+            if op != Op::Add && op != Op::Sub { break; }
+            self.pos += 1;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(self.term()?));
+        }
+        Ok(lhs)
+    }
+
+    fn term(&mut self) -> Result<Expr, String> {
+        let mut lhs = self.unary()?;
+        while let Some(&Token::Op(op)) = self.peek() {
+            if op != Op::Mul && op != Op::Div { break; }
+            self.pos += 1;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(self.unary()?));
+        }
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.peek() == Some(&Token::Op(Op::Sub)) {
+            self.pos += 1;
+            return Ok(Expr::Neg(Box::new(self.unary()?)));
+        }
+        self.atom()
+    }
+
+    fn atom(&mut self) -> Result<Expr, String> {
+        match self.next() {
+            Some(&Token::Number(n)) => Ok(Expr::Number(n)),
+            Some(&Token::Open) => {
+                let inner = self.expr()?;
+                self.expect(Token::Close, "')'")?;
+                Ok(inner)
+            },
+            Some(Token::Ident(name)) => {
+                let name = name.clone();
+                if self.peek() != Some(&Token::Open) {
+                    return Ok(Expr::Series(name));
+                }
+
+                self.pos += 1;
+                let mut args = vec![];
+                if self.peek() != Some(&Token::Close) {
+                    loop {
+                        args.push(self.expr()?);
+                        if self.peek() == Some(&Token::Comma) {
+                            self.pos += 1;
+                        } else {
+                            break;
+                        }
+                    }
+                }
+                self.expect(Token::Close, "')'")?;
+
+                check_call(&name, args.len())?;
+                Ok(Expr::Call(name, args))
+            },
+            _ => Err("expected a number, series or '('".into())
+        }
+    }
+}
+
+fn check_call(name: &str, args: usize) -> Result<(), String> {
+    let ok = match name {
+        "sum" | "avg" | "min" | "max" => args > 0,
+        "abs" => args == 1,
+        "clamp" => args == 3,
+        _ => return Err(format!("unknown function '{}', expected sum, avg, min, max, abs or clamp", name))
+    };
+
+    if ok {
+        Ok(())
+    } else {
+        Err(format!("wrong number of arguments to {}()", name))
+    }
+}
+
+impl Expr {
+    pub fn parse(input: &str) -> Result<Expr, String> {
+        let mut parser = Parser { tokens: tokenize(input)?, pos: 0 };
+        let expr = parser.expr()?;
+
+        if parser.pos < parser.tokens.len() {
+            return Err("unexpected input after expression".into());
+        }
+
+        Ok(expr)
+    }
+
+    /// Names of all series referenced, without duplicates.
+    pub fn series(&self) -> Vec<String> {
+        let mut names = vec![];
+        self.collect_series(&mut names);
+        names
+    }
+
+    fn collect_series(&self, names: &mut Vec<String>) {
+        match *self {
+            Expr::Number(_) => {},
+            Expr::Series(ref name) => {
+                if !names.contains(name) { names.push(name.clone()); }
+            },
+            Expr::Neg(ref e) => e.collect_series(names),
+            Expr::Binary(_, ref l, ref r) => {
+                l.collect_series(names);
+                r.collect_series(names);
+            },
+            Expr::Call(_, ref args) => args.iter().for_each(|a| a.collect_series(names))
+        }
+    }
+
+    /// Evaluates with series values from `lookup`. `None` if a value is
+    /// missing or the result isn't a finite number.
+    pub fn eval<F: Fn(&str) -> Option<f64>>(&self, lookup: &F) -> Option<f64> {
+        let value = match *self {
+            Expr::Number(n) => n,
+            Expr::Series(ref name) => lookup(name)?,
+            Expr::Neg(ref e) => -e.eval(lookup)?,
+            Expr::Binary(op, ref l, ref r) => {
+                let (l, r) = (l.eval(lookup)?, r.eval(lookup)?);
+                match op {
+                    Op::Add => l + r,
+                    Op::Sub => l - r,
+                    Op::Mul => l * r,
+                    Op::Div => l / r
+                }
+            },
+            Expr::Call(ref name, ref args) => {
+                let args = args.iter().map(|a| a.eval(lookup)).collect::<Option<Vec<_>>>()?;
+                match name.as_str() {
+                    "sum" => args.iter().sum(),
+                    "avg" => args.iter().sum::<f64>() / args.len() as f64,
+                    "min" => args.iter().cloned().fold(f64::INFINITY, f64::min),
+                    "max" => args.iter().cloned().fold(f64::NEG_INFINITY, f64::max),
+                    "abs" => args[0].abs(),
+                    "clamp" => args[0].max(args[1]).min(args[2]),
+                    _ => return None
+                }
+            }
+        };
+
+        if value.is_finite() { Some(value) } else { None }
+    }
+}
+
+/// Aligns the input series into `bucket`-second buckets (averaging points
+/// that share a bucket) and evaluates `expr` for every bucket where all the
+/// inputs have a value. The result is newest first, like `DataSource::series`.
+pub fn evaluate(expr: &Expr, name: &str, unit: &str, bucket: u32, inputs: &[Series]) -> Series {
+    let bucket = bucket.max(1);
+
+    let aligned = inputs.iter()
+        .map(|s| {
+            let mut sums: BTreeMap<u32, (f64, u32)> = BTreeMap::new();
+            for m in &s.measurements {
+                let entry = sums.entry(m.timestamp - m.timestamp % bucket).or_insert((0.0, 0));
+                entry.0 += m.measurement;
+                entry.1 += 1;
+            }
+
+            let means = sums.into_iter()
+                .map(|(ts, (sum, n))| (ts, sum / f64::from(n)))
+                .collect::<BTreeMap<_, _>>();
+            (s.name.as_str(), means)
+        })
+        .collect::<Vec<_>>();
+
+    let buckets = match aligned.first() {
+        Some((_, first)) => first.keys()
+            .filter(|ts| aligned.iter().all(|(_, a)| a.contains_key(ts)))
+            .cloned()
+            .collect::<Vec<_>>(),
+        None => vec![]
+    };
+
+    let mut measurements = buckets.into_iter()
+        .filter_map(|ts| {
+            let lookup = |n: &str| aligned.iter()
+                .find(|(name, _)| *name == n)
+                .and_then(|(_, a)| a.get(&ts).cloned());
+            expr.eval(&lookup).map(|v| (ts, v))
+        })
+        .enumerate()
+        .map(|(i, (ts, v))| Measurement { timestamp: ts, run: i as u32 + 1, measurement: v })
+        .collect::<Vec<_>>();
+    measurements.reverse();
+
+    Series {
+        id: 0,
+        name: name.into(),
+        unit: unit.into(),
+        expression: None,
+        meta: Meta::default(),
+        measurements
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(input: &str) -> Option<f64> {
+        let lookup = |name: &str| match name {
+            "a" => Some(6.0),
+            "b.c" => Some(3.0),
+            "d-e" => Some(2.0),
+            _ => None
+        };
+        Expr::parse(input).unwrap().eval(&lookup)
+    }
+
+    fn series(name: &str, points: &[(u32, f64)]) -> Series {
+        Series {
+            id: 0,
+            name: name.into(),
+            unit: String::new(),
+            expression: None,
+            meta: Meta::default(),
+            measurements: points.iter().rev().map(|&(timestamp, measurement)| Measurement { timestamp, run: 0, measurement }).collect()
+        }
+    }
+
+    #[test]
+    fn precedence_and_negation() {
+        assert_eq!(Some(12.0), eval("a + b.c * 2"));
+        assert_eq!(Some(18.0), eval("(a + b.c) * 2"));
+        assert_eq!(Some(-3.0), eval("-b.c"));
+        assert_eq!(Some(1.0), eval("a - b.c - 2"));
+        assert_eq!(Some(150.0), eval("1.5e2"));
+        assert_eq!(Some(1e-5), eval("1e-5"));
+        assert_eq!(Some(3.0 + 2E+3), eval("b.c + 2E+3"));
+        assert_eq!(Some(-98.0), eval("2 - 1e2"));
+    }
+
+    #[test]
+    fn functions_and_quoted_names() {
+        assert_eq!(Some(11.0), eval("sum(a, b.c, \"d-e\")"));
+        assert_eq!(Some(4.5), eval("avg(a, b.c)"));
+        assert_eq!(Some(2.0), eval("min(a, b.c, \"d-e\")"));
+        assert_eq!(Some(3.0), eval("abs(-b.c)"));
+        assert_eq!(Some(1.0), eval("clamp(a / b.c, 0, 1)"));
+    }
+
+    #[test]
+    fn missing_or_non_finite_is_none() {
+        assert_eq!(None, eval("a + nope"));
+        assert_eq!(None, eval("a / 0"));
+    }
+
+    #[test]
+    fn lists_series_once() {
+        assert_eq!(vec!["a".to_string(), "b.c".to_string()], Expr::parse("a / (a + b.c)").unwrap().series());
+    }
+
+    #[test]
+    fn rejects_bad_expressions() {
+        assert!(Expr::parse("").is_err());
+        assert!(Expr::parse("a +").is_err());
+        assert!(Expr::parse("(a").is_err());
+        assert!(Expr::parse("a b").is_err());
+        assert!(Expr::parse("median(a)").is_err());
+        assert!(Expr::parse("clamp(a, 1)").is_err());
+    }
+
+    #[test]
+    fn evaluates_over_shared_buckets() {
+        let errors = series("errors", &[(0, 1.0), (30, 3.0), (60, 4.0), (120, 5.0)]);
+        let requests = series("requests", &[(0, 10.0), (60, 20.0), (180, 1.0)]);
+        let expr = Expr::parse("errors / requests * 100").unwrap();
+
+        let out = evaluate(&expr, "error_rate", "%", 60, &[errors, requests]);
+        let points = out.measurements.iter().map(|m| (m.timestamp, m.measurement)).collect::<Vec<_>>();
+        assert_eq!(vec![(60, 20.0), (0, 20.0)], points);
+        assert_eq!("%", out.unit);
+    }
+}
@@ -0,0 +1,56 @@
+//! Shell-style `*` wildcards for matching series names.
+
+/// Whether `name` matches `pattern`, where `*` matches any run of characters
+/// (including none). Without a `*` the pattern has to match exactly.
+pub fn matches(pattern: &str, name: &str) -> bool {
+    let parts = pattern.split('*').collect::<Vec<_>>();
+
+    if parts.len() == 1 {
+        return pattern == name;
+    }
+
+    let (first, last) = (parts[0], parts[parts.len() - 1]);
+    if name.len() < first.len() + last.len() || !name.starts_with(first) || !name.ends_with(last) {
+        return false;
+    }
+
+    // Match the middle parts left to right, as early as possible
+    let mut rest = &name[first.len()..name.len() - last.len()];
+    for part in &parts[1..parts.len() - 1] {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false
+        }
+    }
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exact_without_wildcards() {
+        assert!(matches("inv.req", "inv.req"));
+        assert!(!matches("inv.req", "inv.req.time"));
+    }
+
+    #[test]
+    fn wildcards() {
+        assert!(matches("inv.*", "inv.req.time"));
+        assert!(matches("*.time", "inv.req.time"));
+        assert!(matches("inv.*.time", "inv.req.time"));
+        assert!(matches("*", ""));
+        assert!(matches("a*b*c", "abc"));
+        assert!(matches("a*b*c", "axxbyybc"));
+        assert!(!matches("a*b*c", "acb"));
+        assert!(!matches("inv.*", "web.inv.req"));
+    }
+
+    #[test]
+    fn prefix_and_suffix_dont_overlap() {
+        assert!(!matches("ab*ba", "aba"));
+        assert!(matches("ab*ba", "abba"));
+    }
+}
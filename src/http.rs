@@ -0,0 +1,299 @@
+//! Just enough HTTP/1.1 to answer local scrapers and scripts.
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+/// Largest request body accepted, bigger ones get a 413
+const MAX_BODY: usize = 1 << 20;
+
+/// Most bytes read for the request line and headers together, more gets a 431
+const MAX_HEAD: u64 = 8 << 10;
+
+/// How long a client gets to send its request or take the response, so a
+/// stalled one can't hold up everyone else
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+pub struct Request {
+    pub method: String,
+    /// Percent-decoded path, without the query string
+    pub path: String,
+    pub query: Vec<(String, String)>,
+    pub body: Vec<u8>
+}
+
+impl Request {
+    /// First value of a query string parameter.
+    pub fn param(&self, key: &str) -> Option<&str> {
+        self.query.iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+pub struct Response {
+    status: u16,
+    content_type: &'static str,
+    body: Vec<u8>
+}
+
+impl Response {
+    pub fn new<B: Into<Vec<u8>>>(status: u16, content_type: &'static str, body: B) -> Self {
+        Response { status, content_type, body: body.into() }
+    }
+
+    pub fn json<B: Into<Vec<u8>>>(status: u16, body: B) -> Self {
+        Self::new(status, "application/json", body)
+    }
+
+    pub fn text<B: Into<Vec<u8>>>(status: u16, body: B) -> Self {
+        Self::new(status, "text/plain; charset=utf-8", body)
+    }
+
+    fn reason(&self) -> &'static str {
+        match self.status {
+            200 => "OK",
+            201 => "Created",
+            204 => "No Content",
+            400 => "Bad Request",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            409 => "Conflict",
+            413 => "Payload Too Large",
+            431 => "Request Header Fields Too Large",
+            _ => "Internal Server Error"
+        }
+    }
+
+    fn write_to<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        write!(
+            out,
+            "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            self.status,
+            self.reason(),
+            self.content_type,
+            self.body.len()
+        )?;
+        out.write_all(&self.body)?;
+        out.flush()
+    }
+}
+
+/// Decodes `%XX` escapes (and `+` as space, for query strings).
+fn decode(input: &str, plus: bool) -> String {
+    let bytes = input.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' if i + 2 < bytes.len() => {
+                let hex = ::std::str::from_utf8(&bytes[i + 1..i + 3]).ok()
+                    .and_then(|h| u8::from_str_radix(h, 16).ok());
+                match hex {
+                    Some(b) => { out.push(b); i += 3; continue; },
+                    None => out.push(b'%')
+                }
+            },
+            b'+' if plus => out.push(b' '),
+            b => out.push(b)
+        }
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Reads a request, or gives the response to send instead when it's one we
+/// won't take.
+fn read_request<R: BufRead>(reader: &mut R) -> io::Result<Result<Request, Response>> {
+    let mut head = reader.by_ref().take(MAX_HEAD);
+
+    let mut line = String::new();
+    head.read_line(&mut line)?;
+
+    let mut parts = line.split_whitespace();
+    let method = parts.next().unwrap_or("").to_string();
+    let target = parts.next().unwrap_or("/");
+
+    if method.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "empty request"));
+    }
+
+    let mut length = 0;
+    loop {
+        let mut header = String::new();
+        if head.read_line(&mut header)? == 0 {
+            if head.limit() == 0 {
+                return Ok(Err(Response::text(431, format!("Request headers over {} bytes\n", MAX_HEAD))));
+            }
+            break;
+        }
+        if header.trim().is_empty() {
+            break;
+        }
+
+        if let Some(i) = header.find(':') {
+            if header[..i].eq_ignore_ascii_case("content-length") {
+                length = header[i + 1..].trim().parse::<usize>().unwrap_or(0);
+            }
+        }
+    }
+
+    if length > MAX_BODY {
+        return Ok(Err(Response::text(413, format!("Request body over {} bytes\n", MAX_BODY))));
+    }
+
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+
+    let mut target = target.splitn(2, '?');
+    let path = decode(target.next().unwrap_or("/"), false);
+    let query = target.next().unwrap_or("")
+        .split('&')
+        .filter(|p| !p.is_empty())
+        .map(|p| {
+            let mut kv = p.splitn(2, '=');
+            (decode(kv.next().unwrap_or(""), true), decode(kv.next().unwrap_or(""), true))
+        })
+        .collect();
+
+    Ok(Ok(Request { method, path, query, body }))
+}
+
+fn handle<F>(stream: TcpStream, listener: usize, handler: &mut F) -> io::Result<()>
+    where F: FnMut(usize, &Request) -> Response
+{
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let request = match read_request(&mut reader)? {
+        Ok(r) => r,
+        Err(response) => {
+            debug!("Refused request -> {}", response.status);
+            return response.write_to(&mut &stream);
+        }
+    };
+
+    let response = handler(listener, &request);
+    debug!("{} {} -> {}", request.method, request.path, response.status);
+
+    response.write_to(&mut &stream)
+}
+
+/// Answers connections on all `listeners` one at a time, forever. The
+/// handler is given the index of the listener each request arrived on.
+pub fn serve<F>(listeners: Vec<TcpListener>, mut handler: F)
+    where F: FnMut(usize, &Request) -> Response
+{
+    // Accept on a thread per listener, but handle everything on this one
+    let (tx, rx) = mpsc::channel();
+    for (i, listener) in listeners.into_iter().enumerate() {
+        let tx = tx.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(s) => if tx.send((i, s)).is_err() { break; },
+                    Err(e) => warn!("Error accepting connection: {}", e)
+                }
+            }
+        });
+    }
+    drop(tx);
+
+    for (i, stream) in rx {
+        if let Err(e) = handle(stream, i, &mut handler) {
+            warn!("Error handling HTTP request: {}", e);
+        }
+    }
+}
+
+/// Fetches a plain `http://` URL and returns the body.
+pub fn get(url: &str) -> io::Result<String> {
+    let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidInput, msg.to_string());
+
+    let rest = match url.strip_prefix("http://") {
+        Some(r) => r,
+        None => return Err(invalid("only http:// URLs are supported"))
+    };
+
+    let (host, path) = match rest.find('/') {
+        Some(i) => rest.split_at(i),
+        None => (rest, "/")
+    };
+
+    if host.is_empty() {
+        return Err(invalid("missing host"));
+    }
+
+    let addr = if host.contains(':') { host.to_string() } else { format!("{}:80", host) };
+    let mut stream = TcpStream::connect(addr)?;
+
+    // HTTP/1.0 keeps the server from chunking the response
+    write!(stream, "GET {} HTTP/1.0\r\nHost: {}\r\nAccept: text/plain\r\n\r\n", path, host)?;
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+
+    let (head, body) = match response.find("\r\n\r\n") {
+        Some(i) => (&response[..i], &response[i + 4..]),
+        None => return Err(io::Error::new(io::ErrorKind::InvalidData, "malformed HTTP response"))
+    };
+
+    let status = head.split_whitespace().nth(1).unwrap_or("");
+    if status != "200" {
+        return Err(io::Error::other(format!("HTTP status {}", status)));
+    }
+
+    Ok(body.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(raw: &str) -> Result<Request, Response> {
+        read_request(&mut raw.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn reads_path_query_and_body() {
+        let request = read("POST /series/inv%2Ereq?create=true&note=a+b HTTP/1.1\r\nContent-Length: 4\r\n\r\nbody").ok().unwrap();
+        assert_eq!("POST", request.method);
+        assert_eq!("/series/inv.req", request.path);
+        assert_eq!(Some("true"), request.param("create"));
+        assert_eq!(Some("a b"), request.param("note"));
+        assert_eq!(b"body".to_vec(), request.body);
+    }
+
+    #[test]
+    fn refuses_large_bodies() {
+        let response = read("POST / HTTP/1.1\r\nContent-Length: 99999999999\r\n\r\n").err().unwrap();
+        assert_eq!(413, response.status);
+    }
+
+    #[test]
+    fn refuses_large_headers() {
+        let long_path = format!("GET /{} HTTP/1.1\r\n\r\n", "a".repeat(10_000));
+        assert_eq!(431, read(&long_path).err().unwrap().status);
+
+        let many = format!("GET / HTTP/1.1\r\n{}\r\n", "X-Pad: 0123456789\r\n".repeat(1000));
+        assert_eq!(431, read(&many).err().unwrap().status);
+    }
+
+    #[test]
+    fn rejects_empty_requests() {
+        assert!(read_request(&mut "".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn decodes_escapes() {
+        assert_eq!("a b/c", decode("a%20b%2Fc", false));
+        assert_eq!("a+b", decode("a+b", false));
+        assert_eq!("a b", decode("a+b", true));
+        assert_eq!("100%", decode("100%", false));
+    }
+}
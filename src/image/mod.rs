@@ -0,0 +1,287 @@
+//! Charts as images, for pasting into documents.
+//!
+//! A `Figure` describes a chart the same way `textplots::Chart` does (layers
+//! of points, reference lines and shaded bands over one Y-axis) and lays it
+//! out as a handful of drawing primitives that the SVG and PNG backends both
+//! understand.
+
+mod font;
+mod raster;
+pub mod svg;
+
+use textplots::Color;
+use textplots::utils;
+use units;
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+pub type Rgb = (u8, u8, u8);
+
+/// How a layer's points are drawn.
+#[derive(Clone, Copy)]
+pub enum Mark {
+    Line,
+    Step,
+    Bar,
+    Points
+}
+
+pub struct Layer {
+    pub points: Vec<(f32, f32)>,
+    pub mark: Mark,
+    pub color: Color
+}
+
+pub struct Figure {
+    pub title: String,
+    pub subtitle: Option<String>,
+    pub unit: String,
+    /// Size in pixels
+    pub width: u32,
+    pub height: u32,
+    pub layers: Vec<Layer>,
+    /// Dashed horizontal lines at Y values
+    pub hrules: Vec<(f32, Color)>,
+    /// Shaded regions between two Y values (either may be infinite)
+    pub bands: Vec<(f32, f32, Color)>,
+    /// Fixed Y-axis ends
+    pub ylimits: (Option<f32>, Option<f32>),
+    /// Keep zero on the Y-axis
+    pub zero: bool,
+    pub log: bool,
+    pub grid: bool
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Anchor {
+    Start,
+    Middle,
+    End
+}
+
+/// What the backends draw, in pixels from the top left.
+pub enum Primitive {
+    Rect { x: f32, y: f32, w: f32, h: f32, fill: Rgb },
+    Line { points: Vec<(f32, f32)>, stroke: Rgb, width: f32, dashed: bool },
+    Dot { x: f32, y: f32, r: f32, fill: Rgb },
+    /// `y` is the baseline, `scale` multiplies the base 11px font size
+    Text { x: f32, y: f32, text: String, scale: u32, anchor: Anchor, fill: Rgb }
+}
+
+const MARGIN_LEFT: f32 = 80.0;
+const MARGIN_RIGHT: f32 = 30.0;
+const MARGIN_TOP: f32 = 64.0;
+const MARGIN_BOTTOM: f32 = 40.0;
+
+const BACKGROUND: Rgb = (255, 255, 255);
+const AXIS: Rgb = (90, 90, 90);
+const GRID: Rgb = (225, 225, 225);
+const TEXT: Rgb = (40, 40, 40);
+
+pub fn rgb(color: Color) -> Rgb {
+    match color {
+        Color::Red => (214, 39, 40),
+        Color::Green => (44, 160, 44),
+        Color::Yellow => (230, 160, 0),
+        Color::Blue => (31, 119, 180),
+        Color::Magenta => (148, 103, 189),
+        // Terminal cyan is hard to see on white
+        Color::Cyan => (23, 130, 190),
+        Color::White => (200, 200, 200),
+        Color::Grey => (127, 127, 127)
+    }
+}
+
+/// A light tint of a colour, for backgrounds.
+fn tint(color: Color) -> Rgb {
+    let (r, g, b) = rgb(color);
+    let mix = |c: u8| (f32::from(c) * 0.2 + 255.0 * 0.8) as u8;
+    (mix(r), mix(g), mix(b))
+}
+
+impl Figure {
+    pub fn new(title: &str, unit: &str) -> Figure {
+        Figure {
+            title: title.into(),
+            subtitle: None,
+            unit: unit.into(),
+            width: 960,
+            height: 480,
+            layers: vec![],
+            hrules: vec![],
+            bands: vec![],
+            ylimits: (None, None),
+            zero: true,
+            log: false,
+            grid: false
+        }
+    }
+
+    fn x_range(&self) -> (f32, f32) {
+        let xs = self.layers.iter().flat_map(|l| l.points.iter().map(|p| p.0));
+        let (min, max) = xs.fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), x| (lo.min(x), hi.max(x)));
+
+        if !min.is_finite() || max <= min {
+            (0.0, 1.0)
+        } else {
+            (min, max)
+        }
+    }
+
+    fn y_range(&self) -> (f32, f32) {
+        let ys = self.layers.iter()
+            .flat_map(|l| l.points.iter().map(|p| p.1))
+            .chain(self.hrules.iter().map(|r| r.0))
+            .chain(if self.zero && !self.log { Some(0.0) } else { None })
+            .filter(|y| y.is_finite() && (!self.log || *y > 0.0));
+        let (mut min, mut max) = ys.fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), y| (lo.min(y), hi.max(y)));
+
+        // A log scale can't reach 0, so limits at or below it are left to the data
+        let usable = |y: &f32| !self.log || *y > 0.0;
+        if let Some(y) = self.ylimits.0.filter(usable) { min = y; }
+        if let Some(y) = self.ylimits.1.filter(usable) { max = y; }
+
+        if !min.is_finite() || !max.is_finite() {
+            return if self.log { (1.0, 10.0) } else { (0.0, 1.0) };
+        }
+
+        if max <= min {
+            let pad = if self.log { min * 0.5 } else { (min.abs() * 0.1).max(1.0) };
+            min -= pad;
+            max = min + 3.0 * pad;
+        }
+
+        (min, max)
+    }
+
+    /// Lays the figure out as primitives, back to front.
+    pub fn primitives(&self) -> Vec<Primitive> {
+        let (w, h) = (self.width as f32, self.height as f32);
+        let (left, right) = (MARGIN_LEFT, w - MARGIN_RIGHT);
+        let (top, bottom) = (MARGIN_TOP, h - MARGIN_BOTTOM);
+
+        let (xmin, xmax) = self.x_range();
+        let (ymin, ymax) = self.y_range();
+        let log = self.log;
+
+        let px = |x: f32| left + (x - xmin) / (xmax - xmin) * (right - left);
+        let py = |y: f32| {
+            let p = if log {
+                (y.log10() - ymin.log10()) / (ymax.log10() - ymin.log10())
+            } else {
+                (y - ymin) / (ymax - ymin)
+            };
+            (bottom - p * (bottom - top)).max(top).min(bottom)
+        };
+
+        let mut out = vec![Primitive::Rect { x: 0.0, y: 0.0, w, h, fill: BACKGROUND }];
+
+        for &(from, to, color) in &self.bands {
+            let (y1, y2) = (py(from.max(ymin).min(ymax)), py(to.max(ymin).min(ymax)));
+            if y1 != y2 {
+                out.push(Primitive::Rect { x: left, y: y1.min(y2), w: right - left, h: (y1 - y2).abs(), fill: tint(color) });
+            }
+        }
+
+        let y_ticks = if log { utils::log_ticks(ymin, ymax) } else { utils::ticks(ymin, ymax, 6) };
+        let x_ticks = utils::ticks(xmin, xmax, 8);
+
+        for &y in &y_ticks {
+            let y_px = py(y);
+            if self.grid {
+                out.push(Primitive::Line { points: vec![(left, y_px), (right, y_px)], stroke: GRID, width: 1.0, dashed: false });
+            }
+            out.push(Primitive::Line { points: vec![(left - 5.0, y_px), (left, y_px)], stroke: AXIS, width: 1.0, dashed: false });
+            out.push(Primitive::Text {
+                x: left - 8.0, y: y_px + 4.0, text: units::format(f64::from(y), &self.unit),
+                scale: 1, anchor: Anchor::End, fill: TEXT
+            });
+        }
+
+        for &x in &x_ticks {
+            let x_px = px(x);
+            out.push(Primitive::Line { points: vec![(x_px, bottom), (x_px, bottom + 5.0)], stroke: AXIS, width: 1.0, dashed: false });
+            out.push(Primitive::Text {
+                x: x_px, y: bottom + 18.0, text: format!("{}", x),
+                scale: 1, anchor: Anchor::Middle, fill: TEXT
+            });
+        }
+
+        for layer in &self.layers {
+            let color = rgb(layer.color);
+            let points = layer.points.iter()
+                .filter(|p| !log || p.1 > 0.0)
+                .map(|&(x, y)| (px(x), py(y)))
+                .collect::<Vec<_>>();
+
+            match layer.mark {
+                Mark::Line => out.push(Primitive::Line { points, stroke: color, width: 2.0, dashed: false }),
+                Mark::Step => {
+                    let mut steps = vec![];
+                    for (i, &(x, y)) in points.iter().enumerate() {
+                        if i > 0 {
+                            steps.push((x, points[i - 1].1));
+                        }
+                        steps.push((x, y));
+                    }
+                    out.push(Primitive::Line { points: steps, stroke: color, width: 2.0, dashed: false });
+                },
+                Mark::Bar => {
+                    // As wide as the closest pair of points allows, kept inside the axes
+                    let gap = points.windows(2).map(|p| (p[1].0 - p[0].0).abs()).fold(right - left, f32::min);
+                    let bar = (gap * 0.7).max(1.0);
+                    let base = if log { bottom } else { py(0.0f32.max(ymin).min(ymax)) };
+                    for &(x, y) in &points {
+                        let (from, to) = ((x - bar / 2.0).max(left), (x + bar / 2.0).min(right));
+                        out.push(Primitive::Rect { x: from, y: y.min(base), w: to - from, h: (y - base).abs(), fill: color });
+                    }
+                },
+                Mark::Points => {
+                    for &(x, y) in &points {
+                        out.push(Primitive::Dot { x, y, r: 2.5, fill: color });
+                    }
+                }
+            }
+        }
+
+        for &(y, color) in &self.hrules {
+            if y >= ymin && y <= ymax {
+                let y_px = py(y);
+                out.push(Primitive::Line { points: vec![(left, y_px), (right, y_px)], stroke: rgb(color), width: 1.5, dashed: true });
+            }
+        }
+
+        out.push(Primitive::Line {
+            points: vec![(left, top), (left, bottom), (right, bottom)],
+            stroke: AXIS, width: 1.0, dashed: false
+        });
+
+        out.push(Primitive::Text { x: left, y: 24.0, text: self.title.clone(), scale: 2, anchor: Anchor::Start, fill: TEXT });
+        if let Some(ref subtitle) = self.subtitle {
+            out.push(Primitive::Text { x: left, y: 42.0, text: subtitle.clone(), scale: 1, anchor: Anchor::Start, fill: AXIS });
+        }
+        if !self.unit.is_empty() {
+            out.push(Primitive::Text { x: left - 8.0, y: top - 10.0, text: self.unit.clone(), scale: 1, anchor: Anchor::End, fill: AXIS });
+        }
+
+        out
+    }
+}
+
+/// Writes the figure as SVG or PNG, going by the file extension.
+pub fn save(figure: &Figure, path: &Path) -> io::Result<()> {
+    let extension = path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .unwrap_or_default();
+
+    let data = match extension.as_str() {
+        "svg" => svg::render(figure).into_bytes(),
+        "png" => raster::render(figure)?,
+        _ => return Err(io::Error::new(io::ErrorKind::InvalidInput, "output file should end in .svg or .png"))
+    };
+
+    fs::write(path, data)
+}
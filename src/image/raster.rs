@@ -0,0 +1,134 @@
+//! Figures as PNG images, drawn pixel by pixel.
+
+use super::{font, Anchor, Figure, Primitive, Rgb};
+
+use png;
+
+use std::io;
+
+/// An RGB pixel buffer.
+struct Bitmap {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>
+}
+
+impl Bitmap {
+    fn new(width: u32, height: u32) -> Bitmap {
+        Bitmap { width, height, pixels: vec![255; (width * height * 3) as usize] }
+    }
+
+    fn put(&mut self, x: i64, y: i64, color: Rgb) {
+        if x < 0 || y < 0 || x >= i64::from(self.width) || y >= i64::from(self.height) {
+            return;
+        }
+
+        let i = ((y as u32 * self.width + x as u32) * 3) as usize;
+        self.pixels[i] = color.0;
+        self.pixels[i + 1] = color.1;
+        self.pixels[i + 2] = color.2;
+    }
+
+    fn rect(&mut self, x: f32, y: f32, w: f32, h: f32, color: Rgb) {
+        let (x1, y1) = (x.round() as i64, y.round() as i64);
+        let (x2, y2) = ((x + w).round() as i64, (y + h).round() as i64);
+
+        for py in y1..y2.max(y1 + 1) {
+            for px in x1..x2.max(x1 + 1) {
+                self.put(px, py, color);
+            }
+        }
+    }
+
+    fn dot(&mut self, x: f32, y: f32, r: f32, color: Rgb) {
+        let reach = r.ceil() as i64;
+        let (cx, cy) = (x.round() as i64, y.round() as i64);
+
+        for dy in -reach..=reach {
+            for dx in -reach..=reach {
+                if (dx * dx + dy * dy) as f32 <= r * r {
+                    self.put(cx + dx, cy + dy, color);
+                }
+            }
+        }
+    }
+
+    /// Draws through `points`, stamping a `width` square every half pixel.
+    /// Dashes are measured along the whole line, so they carry on across
+    /// corners.
+    fn polyline(&mut self, points: &[(f32, f32)], width: f32, dashed: bool, color: Rgb) {
+        let mut travelled = 0.0;
+
+        for pair in points.windows(2) {
+            let ((x1, y1), (x2, y2)) = (pair[0], pair[1]);
+            let length = ((x2 - x1).powi(2) + (y2 - y1).powi(2)).sqrt();
+            let steps = (length * 2.0).ceil().max(1.0) as u32;
+
+            for i in 0..=steps {
+                let t = i as f32 / steps as f32;
+                let along = travelled + t * length;
+
+                if !dashed || along % 10.0 < 6.0 {
+                    self.rect(x1 + t * (x2 - x1) - width / 2.0, y1 + t * (y2 - y1) - width / 2.0, width, width, color);
+                }
+            }
+
+            travelled += length;
+        }
+    }
+
+    fn text(&mut self, x: f32, y: f32, text: &str, scale: u32, anchor: Anchor, color: Rgb) {
+        let width = (text.chars().count() as u32 * font::ADVANCE * scale) as f32;
+        let left = match anchor {
+            Anchor::Start => x,
+            Anchor::Middle => x - width / 2.0,
+            Anchor::End => x - width
+        }.round() as i64;
+        let top = (y.round() as i64) - i64::from(font::HEIGHT * scale);
+        let scale = i64::from(scale);
+
+        for (n, ch) in text.chars().enumerate() {
+            let origin = left + n as i64 * i64::from(font::ADVANCE) * scale;
+
+            for (row, bits) in font::glyph(ch).iter().enumerate() {
+                for column in 0..font::WIDTH {
+                    if bits & (0x10 >> column) == 0 {
+                        continue;
+                    }
+
+                    for dy in 0..scale {
+                        for dx in 0..scale {
+                            self.put(origin + i64::from(column) * scale + dx, top + row as i64 * scale + dy, color);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Renders the figure as PNG file data.
+pub fn render(figure: &Figure) -> io::Result<Vec<u8>> {
+    let mut bitmap = Bitmap::new(figure.width, figure.height);
+
+    for primitive in figure.primitives() {
+        match primitive {
+            Primitive::Rect { x, y, w, h, fill } => bitmap.rect(x, y, w, h, fill),
+            Primitive::Line { points, stroke, width, dashed } => bitmap.polyline(&points, width, dashed, stroke),
+            Primitive::Dot { x, y, r, fill } => bitmap.dot(x, y, r, fill),
+            Primitive::Text { x, y, text, scale, anchor, fill } => bitmap.text(x, y, &text, scale, anchor, fill)
+        }
+    }
+
+    let mut out = vec![];
+    {
+        let mut encoder = png::Encoder::new(&mut out, bitmap.width, bitmap.height);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        let mut writer = encoder.write_header().map_err(io::Error::other)?;
+        writer.write_image_data(&bitmap.pixels).map_err(io::Error::other)?;
+    }
+
+    Ok(out)
+}
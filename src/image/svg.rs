@@ -0,0 +1,76 @@
+//! Figures as SVG documents.
+
+use super::{Anchor, Figure, Primitive, Rgb};
+
+use std::fmt::Write;
+
+fn color((r, g, b): Rgb) -> String {
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Renders the figure as a standalone `<svg>` element, which also works
+/// inline in HTML.
+pub fn render(figure: &Figure) -> String {
+    let mut out = String::new();
+
+    writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}" font-family="sans-serif">"#,
+        w = figure.width, h = figure.height
+    ).unwrap();
+
+    for primitive in figure.primitives() {
+        match primitive {
+            Primitive::Rect { x, y, w, h, fill } => {
+                writeln!(
+                    out,
+                    r#"<rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}" fill="{}"/>"#,
+                    x, y, w, h, color(fill)
+                ).unwrap();
+            },
+            Primitive::Line { points, stroke, width, dashed } => {
+                if points.is_empty() {
+                    continue;
+                }
+
+                let points = points.iter()
+                    .map(|(x, y)| format!("{:.1},{:.1}", x, y))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                let dash = if dashed { r#" stroke-dasharray="6,4""# } else { "" };
+
+                writeln!(
+                    out,
+                    r#"<polyline points="{}" fill="none" stroke="{}" stroke-width="{}" stroke-linejoin="round"{}/>"#,
+                    points, color(stroke), width, dash
+                ).unwrap();
+            },
+            Primitive::Dot { x, y, r, fill } => {
+                writeln!(out, r#"<circle cx="{:.1}" cy="{:.1}" r="{}" fill="{}"/>"#, x, y, r, color(fill)).unwrap();
+            },
+            Primitive::Text { x, y, text, scale, anchor, fill } => {
+                let anchor = match anchor {
+                    Anchor::Start => "start",
+                    Anchor::Middle => "middle",
+                    Anchor::End => "end"
+                };
+
+                writeln!(
+                    out,
+                    r#"<text x="{:.1}" y="{:.1}" font-size="{}" text-anchor="{}" fill="{}">{}</text>"#,
+                    x, y, 8 * scale + 3, anchor, color(fill), escape(&text)
+                ).unwrap();
+            }
+        }
+    }
+
+    out.push_str("</svg>\n");
+    out
+}
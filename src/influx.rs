@@ -0,0 +1,182 @@
+//! Parser for the InfluxDB line protocol.
+//!
+//! `measurement[,tag=value...] field=value[,field=value...] [timestamp]`
+
+/// A single parsed line. Non-numeric fields are dropped.
+pub struct Line {
+    pub measurement: String,
+    pub tags: Vec<(String, String)>,
+    pub fields: Vec<(String, f64)>,
+    /// Unix timestamp in seconds (converted from nanoseconds)
+    pub timestamp: Option<u32>
+}
+
+impl Line {
+    /// Each field is stored as its own `measurement.field` series.
+    pub fn series(&self) -> Vec<(String, f64)> {
+        self.fields.iter()
+            .map(|(field, value)| (format!("{}.{}", self.measurement, field), *value))
+            .collect()
+    }
+}
+
+/// Splits `input` on `sep` unless it's escaped or inside double quotes.
+fn split_unescaped(input: &str, sep: char) -> Vec<String> {
+    let mut parts = vec![];
+    let mut current = String::new();
+    let mut escaped = false;
+    let mut quoted = false;
+
+    for c in input.chars() {
+        if escaped {
+            current.push(c);
+            escaped = false;
+        } else if c == '\\' {
+            current.push(c);
+            escaped = true;
+        } else if c == '"' {
+            current.push(c);
+            quoted = !quoted;
+        } else if c == sep && !quoted {
+            parts.push(current);
+            current = String::new();
+        } else {
+            current.push(c);
+        }
+    }
+    parts.push(current);
+
+    parts
+}
+
+fn unescape(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    let mut chars = input.chars();
+
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            if let Some(n) = chars.next() {
+                out.push(n);
+            }
+        } else {
+            out.push(c);
+        }
+    }
+
+    out
+}
+
+/// Splits `key=value` on the first unescaped `=`.
+fn key_value(input: &str) -> Result<(String, String), String> {
+    let parts = split_unescaped(input, '=');
+    if parts.len() < 2 || parts[0].is_empty() {
+        return Err(format!("expected key=value, got '{}'", input));
+    }
+
+    Ok((unescape(&parts[0]), parts[1..].join("=")))
+}
+
+/// Parses a field value, returning `None` for strings.
+fn field_value(raw: &str) -> Result<Option<f64>, String> {
+    if raw.starts_with('"') {
+        return Ok(None);
+    }
+
+    match raw {
+        "t" | "T" | "true" | "True" | "TRUE" => return Ok(Some(1.0)),
+        "f" | "F" | "false" | "False" | "FALSE" => return Ok(Some(0.0)),
+        _ => {}
+    }
+
+    let number = raw.trim_end_matches(&['i', 'u'][..]);
+    number.parse::<f64>()
+        .map(Some)
+        .map_err(|e| format!("bad field value '{}': {}", raw, e))
+}
+
+pub fn parse(line: &str) -> Result<Line, String> {
+    let sections: Vec<_> = split_unescaped(line.trim(), ' ')
+        .into_iter()
+        .filter(|s| !s.is_empty())
+        .collect();
+
+    if sections.len() < 2 {
+        return Err("missing fields".into());
+    }
+
+    let mut key = split_unescaped(&sections[0], ',').into_iter();
+    let measurement = unescape(&key.next().unwrap_or_default());
+    if measurement.is_empty() {
+        return Err("missing measurement".into());
+    }
+
+    let tags = key
+        .map(|t| key_value(&t).map(|(k, v)| (k, unescape(&v))))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut fields = vec![];
+    for field in split_unescaped(&sections[1], ',') {
+        let (name, raw) = key_value(&field)?;
+        match field_value(&raw)? {
+            Some(v) => fields.push((name, v)),
+            None => debug!("Skipping non-numeric field {}.{}", measurement, name)
+        }
+    }
+
+    let timestamp = match sections.get(2) {
+        Some(ts) => {
+            let ns = ts.parse::<i64>().map_err(|e| format!("bad timestamp '{}': {}", ts, e))?;
+            if ns < 0 || ns / 1_000_000_000 > i64::from(u32::MAX) {
+                return Err(format!("timestamp '{}' out of range", ts));
+            }
+            Some((ns / 1_000_000_000) as u32)
+        },
+        None => None
+    };
+
+    Ok(Line { measurement, tags, fields, timestamp })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_tags_fields_and_timestamp() {
+        let line = parse("cpu,host=web1,region=eu usage=0.5,idle=99i 1700000000000000000").unwrap();
+        assert_eq!("cpu", line.measurement);
+        assert_eq!(vec![("host".to_string(), "web1".to_string()), ("region".to_string(), "eu".to_string())], line.tags);
+        assert_eq!(vec![("cpu.usage".to_string(), 0.5), ("cpu.idle".to_string(), 99.0)], line.series());
+        assert_eq!(Some(1700000000), line.timestamp);
+    }
+
+    #[test]
+    fn booleans_and_strings() {
+        let line = parse("app up=t,down=false,msg=\"a b, c\"").unwrap();
+        assert_eq!(vec![("up".to_string(), 1.0), ("down".to_string(), 0.0)], line.fields);
+        assert_eq!(None, line.timestamp);
+    }
+
+    #[test]
+    fn escaped_separators() {
+        let line = parse("disk\\ io,path=/var\\,log reads=3").unwrap();
+        assert_eq!("disk io", line.measurement);
+        assert_eq!(vec![("path".to_string(), "/var,log".to_string())], line.tags);
+    }
+
+    #[test]
+    fn rejects_out_of_range_timestamps() {
+        assert!(parse("cpu usage=1 -1000000000").is_err());
+        assert!(parse("cpu usage=1 4294967296000000000").is_err());
+        assert!(parse("cpu usage=1 soon").is_err());
+        assert_eq!(Some(u32::MAX), parse("cpu usage=1 4294967295000000000").unwrap().timestamp);
+    }
+
+    #[test]
+    fn rejects_malformed_lines() {
+        assert!(parse("cpu").is_err());
+        assert!(parse(",host=a usage=1").is_err());
+        assert!(parse("cpu usage").is_err());
+        assert!(parse("cpu usage=abc").is_err());
+    }
+}
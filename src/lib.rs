@@ -6,31 +6,59 @@ extern crate structopt;
 #[macro_use] extern crate prettytable;
 extern crate rusqlite;
 extern crate termion;
-extern crate drawille;
 extern crate read_input;
 
-use prettytable::Table;
-use chrono::{Local, TimeZone};
+extern crate serde;
+#[macro_use] extern crate serde_derive;
+#[macro_use] extern crate serde_json;
+extern crate png;
+extern crate toml;
 
 use std::{env, fs, io};
-use std::io::{BufRead, Read};
+use std::io::Read;
 use std::path::PathBuf;
 
+mod anomaly;
+mod api;
+mod check;
 mod cli;
 mod cmd;
+mod config;
 mod data;
+mod duration;
+mod expr;
+mod glob;
+mod http;
+mod image;
+mod influx;
 mod menu;
+mod meta;
+mod prometheus;
+mod sparkline;
+mod stats;
 mod textplots;
+mod transform;
+mod tree;
+mod trend;
+mod units;
 
-use cli::Command;
-use menu::Menu;
+use cli::{ColorMode, Command, ExportFormat, SeriesCommand};
 use data::DataSource;
-use textplots::{Chart, Shape, Plot};
+use meta::Meta;
 
 pub fn run() -> i32 {
     let args = cli::init();
 
-    let data_root = match args.data_root {
+    let settings = match config::load(args.config) {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("Invalid config file {}", e);
+            return 1;
+        }
+    };
+
+    let data_root = args.data_root.or_else(|| settings.data_root.as_ref().map(|p| config::expand(p)));
+    let data_root = match data_root {
         Some(path) => path,
         None => {
             match env::var("HOME") {
@@ -63,7 +91,9 @@ pub fn run() -> i32 {
         }
     };
 
-    let log_file = args.log_file.unwrap_or_else(|| data_root.join("trk.log") );
+    let log_file = args.log_file
+        .or_else(|| settings.log_file.as_ref().map(|p| config::expand(p)))
+        .unwrap_or_else(|| data_root.join("trk.log") );
 
     // Init logger
     fern::Dispatch::new()
@@ -75,12 +105,25 @@ pub fn run() -> i32 {
                 m
             ))
         })
-        .level(log::LevelFilter::Warn)
+        .level(args.log_level.or(settings.log_level).unwrap_or(log::LevelFilter::Warn))
         .chain(fern::log_file(log_file).unwrap())
         .apply()
         .unwrap();
 
-    let db = match DataSource::new(&data_root, &args.file) {
+    let color = match args.color.or(settings.color).unwrap_or(ColorMode::Auto) {
+        ColorMode::Always => true,
+        ColorMode::Never => false,
+        ColorMode::Auto => {
+            let no_color = env::var_os("NO_COLOR").map(|v| !v.is_empty()).unwrap_or(false);
+            !no_color && termion::is_tty(&io::stdout())
+        }
+    };
+
+    let file = args.file
+        .or_else(|| settings.file.clone())
+        .unwrap_or_else(|| PathBuf::from("default"));
+
+    let db = match DataSource::new(&data_root, &file) {
         Ok(db) => db,
         Err(e) => {
             do_error("Error initialising data source", e);
@@ -89,112 +132,34 @@ pub fn run() -> i32 {
     };
 
     match args.subcmd {
-        Command::Plot { series, points, table } => {
-            let series = match series {
-                Some(s) => s,
-                None => {
-                    match db.list_series() {
-                        Ok(list) => {
-                            let list: Vec<_> = list.iter().map(|s| s.name.as_str()).collect();
-                            Menu::from_vec("Select series to plot:", &list).show().to_owned()
-                        },
-                        Err(e) => {
-                            do_error("Error getting series list", e);
-                            std::process::exit(1);
-                        }
-                    }
-                }
+        Command::Plot {
+            series, expression, bucket, points, table, transforms, style, trend, target, forecast,
+            grid, no_grid, warn, crit, hlines, ymin, ymax, no_zero, log, width, height, out
+        } => {
+            let points = points.or(settings.plot.points).unwrap_or(50);
+            let opts = cmd::plot::Options {
+                table, transforms, style, trend, target, forecast,
+                grid: !no_grid && (grid || settings.plot.grid),
+                warn, crit, hlines, ymin, ymax, no_zero, log,
+                width: width.or(settings.plot.width),
+                height: height.or(settings.plot.height),
+                out, color,
+                settings: &settings
             };
 
-            match db.series(&series, points) {
-                Ok(Some(data)) => {
-                    println!("# Series: {}\n", series);
-
-                    let mut prepared = data.measurements.iter()
-                        .rev()
-                        .enumerate()
-                        .map(|(i, point)| {
-                            ((i) as f32, point.measurement as f32)
-                        })
-                        .collect::<Vec<_>>();
-                    prepared.insert(0, (0.0f32, 0.0f32));
-
-                    let x_width = prepared.len() as f32;
-
-                    Chart::new(150, 60, 0.0, x_width)
-                        .y_label(&data.unit)
-                        .lineplot( Shape::Lines(prepared.as_slice()) )
-                        .nice();
-
-                    if table {
-                        let mut table = Table::new();
-                        table.add_row(row!["#", "TIMESTAMP", "VALUE"]);
-
-                        data.measurements.iter()
-                            .enumerate()
-                            .for_each(|(i, p)| {
-                                let run = data.measurements.len() - i;
-                                let ts = Local.timestamp(p.timestamp.into(), 0);
-
-                                table.add_row(row![
-                                    run,
-                                    ts.format("%Y-%m-%d %H:%M:%S"),
-                                    p.measurement
-                                ]);
-                            });
-                        
-                        println!(""); // newline
-                        table.printstd();
-                    }
-                },
-                Ok(None) => {
-                    println!("Series not found");
-                },
-                Err(e) => {
-                    do_error("Error querying series data", e);
-                    return 1;
-                }
-            }
+            return match expression {
+                Some(e) => cmd::plot::plot_expression(&db, &e, bucket, points, &opts),
+                None => cmd::plot::plot(&db, &data_root, &series, points, &opts)
+            };
         },
-        Command::AddBulk { create } => {
-            let stdin = io::stdin();
-            let stdin = stdin.lock();
-
-            stdin.lines()
-                .for_each(|line| {
-                    let line = line.unwrap();
-
-                    if line.is_empty() { return; }
-
-                    let idx = match line.find('=') {
-                        Some(i) => i,
-                        None => {
-                            warn!("Invalid input format: '{}'", line);
-                            return;
-                        }
-                    };
-
-                    let (series, value) = line.split_at(idx);
-                    let value = match value.trim_start_matches('=').parse::<f64>() {
-                        Ok(v) => v,
-                        Err(e) => {
-                            do_error("Error parsing value", e);
-                            std::process::exit(1);
-                        }
-                    };
-
-                    // println!("{} => {}", series, value.trim_start_matches('='));
+        Command::AddBulk { format, create } => cmd::measurement::bulk(&db, format, create),
+        Command::AddMeasurement { series, value, create, check, warn, crit } => {
+            // A value belongs to one series, and a typo could create them by the dozen
+            if series.contains('*') {
+                eprintln!("add takes a single series name, not a pattern like '{}'", series);
+                return 1;
+            }
 
-                    match db.measure(&series, value, create) {
-                        Ok(_) => {},
-                        Err(e) => {
-                            do_error(&format!("Error adding measurement to {}", series), e);
-                            std::process::exit(1);
-                        }
-                    };
-                });
-        },
-        Command::AddMeasurement { series, value, create } => {
             let value = match value {
                 Some(v) => v,
                 None => {
@@ -223,22 +188,115 @@ pub fn run() -> i32 {
 
             match db.measure(&series, value, create) {
                 Ok(_) => {
-                    debug!("Added {} to series {} in source {}", value, series, args.file.display());
+                    debug!("Added {} to series {} in source {}", value, series, file.display());
                 },
                 Err(e) => {
                     do_error("Error adding measurement to series", e);
                     return 1;
                 }
             };
+
+            if check {
+                return cmd::check::check(&db, &series, warn, crit, None, None);
+            }
+        },
+        Command::Check { series, warn, crit, over, agg } => {
+            return each(&db, &series, |series| cmd::check::check(&db, series, warn, crit, over, agg));
+        },
+        Command::Anomalies { series, points, method, threshold, plot } => {
+            return each(&db, &series, |series| {
+                cmd::anomalies::anomalies(&db, series, points, method, threshold, plot, color)
+            });
+        },
+        Command::List { all_files } => {
+            return cmd::list::list(&db, &data_root, all_files);
+        },
+        Command::Tree { prefix, depth } => {
+            return cmd::tree::tree(&db, prefix, depth);
+        },
+        Command::Spark { pattern, points } => {
+            return cmd::spark::spark(&db, pattern, points);
+        },
+        Command::Dash { name } => {
+            return cmd::dash::dash(&db, &settings, &name, color);
+        },
+        Command::Report { patterns, points, out } => {
+            return cmd::report::report(&db, &patterns, points, out);
+        },
+        Command::Histogram { series, points, bins } => {
+            return each(&db, &series, |series| cmd::plot::histogram(&db, series, points, bins, color));
+        },
+        Command::Compare { series, window, offset } => {
+            return each(&db, &series, |series| cmd::compare::compare(&db, series, window, offset, color));
+        },
+        Command::AddSeries { name, unit, expression, bucket } => cmd::series::create(&db, name, unit, expression, bucket),
+        Command::Series { cmd: SeriesCommand::Set {
+            series, display_name, description, precision, min, max, warn, crit, agg, kind, unset
+        } } => {
+            let changes = Meta {
+                display_name, description, precision, min, max, warn, crit,
+                aggregation: agg,
+                kind
+            };
+            return each(&db, &series, |series| cmd::series::set(&db, series, changes.clone(), &unset));
+        },
+        Command::Series { cmd: SeriesCommand::Show { series } } => {
+            return each(&db, &series, |series| cmd::series::show(&db, series));
         },
-        Command::AddSeries { name, unit } => cmd::series::create(&db, name, unit),
-        Command::DeleteSeries { series } => cmd::series::delete(&db, series)
+        Command::DeleteSeries { series: Some(ref series), yes } if series.contains('*') => {
+            return cmd::series::delete_matching(&db, series, yes);
+        },
+        Command::DeleteSeries { series, .. } => cmd::series::delete(&db, series),
+        Command::Statsd { listen, flush, create } => cmd::statsd::listen(&db, &listen, flush, create),
+        Command::Graphite { listen, create } => cmd::graphite::listen(&db, &listen, create),
+        Command::Serve { listen, prometheus } => cmd::serve::serve(&db, listen, prometheus),
+        Command::Export { format, transforms, out } => {
+            let format = format.or(settings.export.format).unwrap_or(ExportFormat::Prometheus);
+            cmd::export::export(&db, format, &transforms, out)
+        },
+        Command::ScrapeProm { source, metrics, labels, create } => {
+            cmd::scrape::scrape(&db, source, metrics, labels, create)
+        }
     }
 
     
     0
 }
 
+/// Runs `f` on each series matching `pattern` if it has a `*` in it (eg
+/// `inv.*`), otherwise on `pattern` as it is. Returns the worst exit code.
+fn each<F: FnMut(&str) -> i32>(db: &DataSource, pattern: &str, mut f: F) -> i32 {
+    if !pattern.contains('*') {
+        return f(pattern);
+    }
+
+    let names = match db.matching(pattern) {
+        Ok(n) => n,
+        Err(e) => {
+            do_error("Error getting series list", e);
+            return 1;
+        }
+    };
+
+    if names.is_empty() {
+        eprintln!("No series match '{}'", pattern);
+        return 1;
+    }
+
+    names.iter().map(|n| f(n)).max_by_key(|c| severity(*c)).unwrap_or(0)
+}
+
+/// Ranks exit codes the way Nagios does: critical, then warning, then
+/// unknown, then ok.
+fn severity(code: i32) -> u8 {
+    match code {
+        0 => 0,
+        3 => 1,
+        1 => 2,
+        _ => 3
+    }
+}
+
 pub fn do_error<E: std::error::Error>(msg: &str, e: E) {
     error!("{}", &format!("{}: {}", msg, e));
     eprintln!("{}", &format!("{}! Check log file for detail.", msg));
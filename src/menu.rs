@@ -1,4 +1,6 @@
-use termion::{color, cursor};
+use tree::Node;
+
+use termion::{clear, color, cursor};
 use termion::event::Key;
 use termion::input::TermRead;
 use termion::raw::IntoRawMode;
@@ -10,6 +12,12 @@ pub struct Menu {
     note: String,
     items: Vec<String>,
     selected: usize,
+    /// Set when browsing dotted names a level at a time
+    tree: Option<Node>,
+    /// The level shown, eg `inv.req`
+    level: String,
+    /// Full name behind each item at this level, and whether it's a branch
+    entries: Vec<(String, bool)>
 }
 
 #[allow(unused)]
@@ -18,7 +26,10 @@ impl Menu {
         Menu {
             note: note.into(),
             items: vec![],
-            selected: 0
+            selected: 0,
+            tree: None,
+            level: String::new(),
+            entries: vec![]
         }
     }
 
@@ -30,7 +41,43 @@ impl Menu {
         Menu {
             note: note.into(),
             items,
-            selected: 0
+            selected: 0,
+            tree: None,
+            level: String::new(),
+            entries: vec![]
+        }
+    }
+
+    /// A menu over dotted names which opens one level of the hierarchy at a
+    /// time, eg `inv` then `req` then `time`.
+    pub fn from_tree(note: &str, names: &[&str]) -> Self {
+        let mut menu = Menu::new(note);
+        menu.tree = Some(Node::build(names.iter().cloned()));
+        menu.open("");
+        menu
+    }
+
+    /// Lists the level at `path` of the tree.
+    fn open(&mut self, path: &str) {
+        self.items.clear();
+        self.entries.clear();
+        self.selected = 0;
+        self.level = path.to_string();
+
+        let node = match self.tree.as_ref().and_then(|t| t.find(path)) {
+            Some(n) => n,
+            None => return
+        };
+
+        for (part, child) in &node.children {
+            if child.series {
+                self.items.push(part.clone());
+                self.entries.push((child.path.clone(), false));
+            }
+            if !child.children.is_empty() {
+                self.items.push(format!("{}.* ({}) >", part, child.count()));
+                self.entries.push((child.path.clone(), true));
+            }
         }
     }
 
@@ -59,9 +106,22 @@ impl Menu {
                     };
                 },
                 Key::Up => {
-                    match self.selected.checked_sub(1) {
-                        Some(n) => self.selected = n,
-                        None => {}
+                    if let Some(n) = self.selected.checked_sub(1) {
+                        self.selected = n;
+                    }
+                },
+                Key::Char('\n') | Key::Right if self.is_branch() => {
+                    let path = self.entries[self.selected].0.clone();
+                    self.open(&path);
+                },
+                Key::Left | Key::Backspace if !self.level.is_empty() => {
+                    let from = self.level.clone();
+                    let parent = from.rfind('.').map(|i| &from[..i]).unwrap_or("");
+                    self.open(parent);
+
+                    // Back on the branch we came out of
+                    if let Some(i) = self.entries.iter().position(|e| e.1 && e.0 == from) {
+                        self.selected = i;
                     }
                 },
                 Key::Char('\n') => {
@@ -79,12 +139,22 @@ impl Menu {
             self.redraw(&mut screen);
         }
 
-        &self.items[self.selected]
+        match self.entries.get(self.selected) {
+            Some(entry) => &entry.0,
+            None => &self.items[self.selected]
+        }
+    }
+
+    fn is_branch(&self) -> bool {
+        self.entries.get(self.selected).map(|e| e.1).unwrap_or(false)
     }
 
     fn redraw<W: Write>(&mut self, screen: &mut W) {
-        write!(screen, "{}", cursor::Goto(3, 2));
+        write!(screen, "{}{}", clear::All, cursor::Goto(3, 2));
         write!(screen, "{}", self.note);
+        if !self.level.is_empty() {
+            write!(screen, " {}.*", self.level);
+        }
         
         for (i, item) in self.items.iter().enumerate() {
             let line = 4u16 + i as u16;
@@ -0,0 +1,67 @@
+//! Per-series metadata: how to describe, show and check a series.
+
+use check::Threshold;
+use stats::Aggregation;
+
+use std::fmt;
+use std::str::FromStr;
+
+/// What a series measures, as in Prometheus.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Kind {
+    /// A value that goes up and down
+    Gauge,
+    /// A running total that only goes up
+    Counter
+}
+
+impl FromStr for Kind {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "gauge" => Ok(Kind::Gauge),
+            "counter" => Ok(Kind::Counter),
+            k => Err(format!("unknown kind '{}', expected gauge or counter", k))
+        }
+    }
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            Kind::Gauge => "gauge",
+            Kind::Counter => "counter"
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct Meta {
+    /// Shown instead of the name in titles
+    pub display_name: Option<String>,
+    pub description: Option<String>,
+    /// Decimal places to show values with
+    pub precision: Option<usize>,
+    /// Expected range, used for the Y-axis
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    /// Thresholds for `check` and to mark on plots
+    pub warn: Option<Threshold>,
+    pub crit: Option<Threshold>,
+    /// How to combine windows of values, eg for `check --over`
+    pub aggregation: Option<Aggregation>,
+    /// A gauge unless set
+    pub kind: Option<Kind>
+}
+
+impl Meta {
+    /// Formats a value to the series' precision, if it has one.
+    pub fn format(&self, value: f64) -> String {
+        match self.precision {
+            Some(p) => format!("{:.*}", p, value),
+            None => value.to_string()
+        }
+    }
+}
@@ -0,0 +1,349 @@
+//! Prometheus text exposition format.
+
+use data::DataSource;
+use meta::Kind;
+use transform::{self, Transform};
+use units;
+
+use rusqlite;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// Turns a series name and unit into a valid metric name, with the base unit
+/// as suffix (eg `inv.req.time` in `ms` becomes `inv_req_time_seconds`).
+pub fn metric_name(series: &str, unit: &str) -> String {
+    let mut name: String = series.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == ':' { c } else { '_' })
+        .collect();
+
+    if name.chars().next().map(|c| c.is_ascii_digit()).unwrap_or(true) {
+        name.insert(0, '_');
+    }
+
+    if let Some(base) = units::base_unit(unit) {
+        let suffix: String = base.name.chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+
+        if !name.ends_with(&format!("_{}", suffix)) {
+            name.push('_');
+            name.push_str(&suffix);
+        }
+    }
+
+    name
+}
+
+fn label_name(key: &str) -> String {
+    let mut name: String = key.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '_' })
+        .collect();
+
+    if name.chars().next().map(|c| c.is_ascii_digit()).unwrap_or(true) {
+        name.insert(0, '_');
+    }
+
+    name
+}
+
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// Formats a sample value, spelling infinities and NaN the way the format
+/// expects.
+fn format_value(value: f64) -> String {
+    if value.is_nan() {
+        "NaN".into()
+    } else if value.is_infinite() {
+        if value > 0.0 { "+Inf".into() } else { "-Inf".into() }
+    } else {
+        value.to_string()
+    }
+}
+
+/// Renders the latest value of every series, after applying `transforms`.
+///
+/// Series whose names clean up to a metric already written (eg `a.b` and
+/// `a_b`) are skipped with a warning, as Prometheus rejects the whole scrape
+/// over a repeated metric.
+pub fn render(db: &DataSource, transforms: &[Transform]) -> rusqlite::Result<String> {
+    let mut out = String::new();
+    let mut written: HashMap<String, String> = HashMap::new();
+
+    // Transforms like rate need some history to work with
+    let points = if transforms.is_empty() { 1 } else { u8::MAX };
+
+    for series in db.list_series()? {
+        let mut data = match db.series(&series.name, points)? {
+            Some(d) => d,
+            None => continue
+        };
+        transform::apply(&mut data, transforms);
+
+        let latest = match data.measurements.first() {
+            Some(m) => m,
+            None => continue
+        };
+
+        let counter = data.meta.kind == Some(Kind::Counter);
+        let mut name = metric_name(&data.name, &data.unit);
+        if counter && !name.ends_with("_total") {
+            name.push_str("_total");
+        }
+
+        if let Some(other) = written.get(&name) {
+            warn!("Skipping {} in the export, {} is already written as {}", data.name, other, name);
+            continue;
+        }
+        written.insert(name.clone(), data.name.clone());
+
+        let value = match units::base_unit(&data.unit) {
+            Some(base) => base.convert(latest.measurement),
+            None => latest.measurement
+        };
+
+        let labels = db.tags(data.id, latest.run)?
+            .iter()
+            .map(|(k, v)| format!("{}=\"{}\"", label_name(k), escape_label(v)))
+            .collect::<Vec<_>>();
+        let labels = if labels.is_empty() {
+            String::new()
+        } else {
+            format!("{{{}}}", labels.join(","))
+        };
+
+        let help = if data.unit.is_empty() {
+            data.name.clone()
+        } else {
+            format!("{} ({})", data.name, data.unit)
+        };
+        let help = match data.meta.description {
+            Some(ref d) => format!("{}: {}", help, d.replace('\\', "\\\\").replace('\n', "\\n")),
+            None => help
+        };
+
+        writeln!(out, "# HELP {} trk series {}", name, help).unwrap();
+        writeln!(out, "# TYPE {} {}", name, if counter { "counter" } else { "gauge" }).unwrap();
+        writeln!(out, "{}{} {}", name, labels, format_value(value)).unwrap();
+    }
+
+    Ok(out)
+}
+
+/// A single sample parsed from exposition text.
+pub struct Sample {
+    pub name: String,
+    pub labels: Vec<(String, String)>,
+    pub value: f64,
+    pub timestamp: Option<u32>
+}
+
+fn parse_labels(input: &str) -> Result<Vec<(String, String)>, String> {
+    let mut labels = vec![];
+    let mut chars = input.chars().peekable();
+
+    loop {
+        while chars.peek().map(|c| c.is_whitespace() || *c == ',').unwrap_or(false) {
+            chars.next();
+        }
+
+        let key: String = chars.by_ref().take_while(|c| *c != '=').collect();
+        let key = key.trim().to_string();
+        if key.is_empty() {
+            break;
+        }
+
+        if chars.next() != Some('"') {
+            return Err(format!("expected quoted value for label '{}'", key));
+        }
+
+        let mut value = String::new();
+        loop {
+            match chars.next() {
+                Some('\\') => match chars.next() {
+                    Some('n') => value.push('\n'),
+                    Some(c) => value.push(c),
+                    None => return Err("unterminated label value".into())
+                },
+                Some('"') => break,
+                Some(c) => value.push(c),
+                None => return Err("unterminated label value".into())
+            }
+        }
+
+        labels.push((key, value));
+    }
+
+    Ok(labels)
+}
+
+/// Parses one line of Prometheus or OpenMetrics text, returning `None` for
+/// comments and blank lines.
+pub fn parse_line(line: &str) -> Option<Result<Sample, String>> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+
+    Some(parse_sample(line))
+}
+
+/// Index of the `}` closing the label set opened at `open`, skipping any in
+/// quoted label values. Exemplars (`# {trace_id="x"} 1`) come after it.
+fn label_set_end(line: &str, open: usize) -> Option<usize> {
+    let mut quoted = false;
+    let mut escaped = false;
+
+    for (i, c) in line[open + 1..].char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            '}' if !quoted => return Some(open + 1 + i),
+            _ => {}
+        }
+    }
+
+    None
+}
+
+fn parse_sample(line: &str) -> Result<Sample, String> {
+    let (name, labels, rest) = match line.find('{') {
+        Some(open) => {
+            let close = label_set_end(line, open).ok_or("unterminated label set")?;
+            (&line[..open], parse_labels(&line[open + 1..close])?, &line[close + 1..])
+        },
+        None => {
+            let end = line.find(char::is_whitespace).ok_or("missing value")?;
+            (&line[..end], vec![], &line[end..])
+        }
+    };
+
+    // Anything after a `#` is an OpenMetrics exemplar
+    let rest = rest.split('#').next().unwrap_or("");
+    let mut fields = rest.split_whitespace();
+    let value = fields.next()
+        .ok_or("missing value")?
+        .parse::<f64>()
+        .map_err(|e| format!("bad value: {}", e))?;
+
+    // Prometheus text uses milliseconds, OpenMetrics uses seconds
+    let timestamp = match fields.next() {
+        Some(ts) => {
+            let ts = ts.parse::<f64>().map_err(|e| format!("bad timestamp: {}", e))?;
+            let secs = if ts > 1e11 { ts / 1000.0 } else { ts };
+            if !secs.is_finite() || secs < 0.0 || secs > f64::from(u32::MAX) {
+                return Err(format!("timestamp {} out of range", ts));
+            }
+            Some(secs.trunc() as u32)
+        },
+        None => None
+    };
+
+    Ok(Sample { name: name.trim().into(), labels, value, timestamp })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data::tests::scratch;
+
+    #[test]
+    fn metric_names() {
+        assert_eq!("inv_req_time_seconds", metric_name("inv.req.time", "ms"));
+        assert_eq!("disk_used_bytes", metric_name("disk.used", "MB"));
+        assert_eq!("_5xx_errors", metric_name("5xx.errors", ""));
+        assert_eq!("job:rate", metric_name("job:rate", ""));
+    }
+
+    #[test]
+    fn non_finite_values() {
+        assert_eq!("+Inf", format_value(f64::INFINITY));
+        assert_eq!("-Inf", format_value(f64::NEG_INFINITY));
+        assert_eq!("NaN", format_value(f64::NAN));
+        assert_eq!("1.5", format_value(1.5));
+    }
+
+    #[test]
+    fn render_skips_colliding_names() {
+        let db = scratch("prometheus-collisions");
+        db.measure("a.b", 1.0, true).unwrap();
+        db.measure("a_b", 2.0, true).unwrap();
+        db.measure("c", f64::INFINITY, true).unwrap();
+
+        let out = render(&db, &[]).unwrap();
+        assert_eq!(1, out.matches("# TYPE a_b gauge").count());
+        assert!(out.contains("a_b 1\n"));
+        assert!(!out.contains("a_b 2\n"));
+        assert!(out.contains("c +Inf\n"));
+    }
+
+    fn sample(line: &str) -> Sample {
+        parse_sample(line).unwrap()
+    }
+
+    #[test]
+    fn parses_plain_samples() {
+        let s = sample("node_load1 0.42");
+        assert_eq!("node_load1", s.name);
+        assert!(s.labels.is_empty());
+        assert_eq!(0.42, s.value);
+        assert_eq!(None, s.timestamp);
+    }
+
+    #[test]
+    fn parses_labels_with_escapes() {
+        let s = sample(r#"http_requests_total{code="500",path="/a \"b\" {c}\n"} 3"#);
+        assert_eq!("http_requests_total", s.name);
+        assert_eq!(vec![
+            ("code".to_string(), "500".to_string()),
+            ("path".to_string(), "/a \"b\" {c}\n".to_string())
+        ], s.labels);
+        assert_eq!(3.0, s.value);
+    }
+
+    #[test]
+    fn timestamps_in_milliseconds_or_seconds() {
+        assert_eq!(Some(1700000000), sample("up 1 1700000000000").timestamp);
+        assert_eq!(Some(1700000000), sample("up 1 1700000000.5").timestamp);
+    }
+
+    #[test]
+    fn rejects_out_of_range_timestamps() {
+        assert!(parse_sample("up 1 -1").is_err());
+        assert!(parse_sample("up 1 NaN").is_err());
+        assert!(parse_sample("up 1 +Inf").is_err());
+        assert!(parse_sample("up 1 4294967296").is_err());
+        assert!(parse_sample("up 1 4294967296000").is_err());
+        assert_eq!(Some(u32::MAX), sample("up 1 4294967295").timestamp);
+    }
+
+    #[test]
+    fn ignores_exemplars() {
+        let s = sample(r#"rpc_seconds_bucket{le="0.5"} 12 # {trace_id="abc"} 0.3 1700000000"#);
+        assert_eq!(vec![("le".to_string(), "0.5".to_string())], s.labels);
+        assert_eq!(12.0, s.value);
+        assert_eq!(None, s.timestamp);
+    }
+
+    #[test]
+    fn special_values() {
+        assert!(sample("x +Inf").value.is_infinite());
+        assert!(sample("x NaN").value.is_nan());
+    }
+
+    #[test]
+    fn skips_comments_and_blank_lines() {
+        assert!(parse_line("# TYPE up gauge").is_none());
+        assert!(parse_line("   ").is_none());
+    }
+
+    #[test]
+    fn rejects_malformed_samples() {
+        assert!(parse_sample("up").is_err());
+        assert!(parse_sample("up{job=\"a\" 1").is_err());
+        assert!(parse_sample("up{job=a} 1").is_err());
+        assert!(parse_sample("up{} x").is_err());
+    }
+}
@@ -0,0 +1,40 @@
+//! One-line charts from Unicode block characters, for when there's no room
+//! (or no terminal) for a full `Chart`.
+
+const BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Averages `values` down to at most `width` buckets.
+fn resample(values: &[f64], width: usize) -> Vec<f64> {
+    if width == 0 || values.len() <= width {
+        return values.to_vec();
+    }
+
+    (0..width)
+        .map(|i| {
+            let from = i * values.len() / width;
+            let to = ((i + 1) * values.len() / width).max(from + 1);
+            values[from..to].iter().sum::<f64>() / (to - from) as f64
+        })
+        .collect()
+}
+
+/// Renders `values` (oldest first) in at most `width` characters, scaled
+/// between their own min and max.
+pub fn render(values: &[f64], width: usize) -> String {
+    let values = resample(values, width);
+
+    let min = values.iter().cloned().fold(f64::INFINITY, f64::min);
+    let max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    let range = max - min;
+
+    values.iter()
+        .map(|v| {
+            if range > 0.0 {
+                let level = ((v - min) / range * (BLOCKS.len() - 1) as f64).round() as usize;
+                BLOCKS[level.min(BLOCKS.len() - 1)]
+            } else {
+                BLOCKS[BLOCKS.len() / 2]
+            }
+        })
+        .collect()
+}
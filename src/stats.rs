@@ -0,0 +1,147 @@
+//! Summary statistics over measurement values.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+/// How to reduce a window of values to a single number.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Aggregation {
+    Avg,
+    Min,
+    Max,
+    Sum,
+    Count,
+    Median,
+    Last
+}
+
+impl FromStr for Aggregation {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "avg" | "mean" => Ok(Aggregation::Avg),
+            "min" => Ok(Aggregation::Min),
+            "max" => Ok(Aggregation::Max),
+            "sum" => Ok(Aggregation::Sum),
+            "count" => Ok(Aggregation::Count),
+            "median" => Ok(Aggregation::Median),
+            "last" => Ok(Aggregation::Last),
+            a => Err(format!("unknown aggregation '{}', expected avg, min, max, sum, count, median or last", a))
+        }
+    }
+}
+
+impl fmt::Display for Aggregation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            Aggregation::Avg => "avg",
+            Aggregation::Min => "min",
+            Aggregation::Max => "max",
+            Aggregation::Sum => "sum",
+            Aggregation::Count => "count",
+            Aggregation::Median => "median",
+            Aggregation::Last => "last"
+        };
+        write!(f, "{}", name)
+    }
+}
+
+pub fn mean(values: &[f64]) -> Option<f64> {
+    if values.is_empty() {
+        None
+    } else {
+        Some(values.iter().sum::<f64>() / values.len() as f64)
+    }
+}
+
+pub fn median(values: &[f64]) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+
+    let mid = sorted.len() / 2;
+    if sorted.len() % 2 == 0 {
+        Some((sorted[mid - 1] + sorted[mid]) / 2.0)
+    } else {
+        Some(sorted[mid])
+    }
+}
+
+/// Reduces `values` (oldest first) with `agg`. `None` if there's nothing to
+/// aggregate, except for `Count`.
+pub fn aggregate(values: &[f64], agg: Aggregation) -> Option<f64> {
+    match agg {
+        Aggregation::Count => Some(values.len() as f64),
+        _ if values.is_empty() => None,
+        Aggregation::Avg => mean(values),
+        Aggregation::Min => values.iter().cloned().fold(None, |m: Option<f64>, v| Some(m.map_or(v, |m| m.min(v)))),
+        Aggregation::Max => values.iter().cloned().fold(None, |m: Option<f64>, v| Some(m.map_or(v, |m| m.max(v)))),
+        Aggregation::Sum => Some(values.iter().sum()),
+        Aggregation::Median => median(values),
+        Aggregation::Last => values.last().cloned()
+    }
+}
+
+/// The usual statistics over a window of values.
+pub struct Summary {
+    pub count: usize,
+    pub sum: f64,
+    pub mean: f64,
+    pub median: f64,
+    pub min: f64,
+    pub max: f64,
+    pub last: f64
+}
+
+impl Summary {
+    /// Summarises `values` (oldest first), `None` if there aren't any.
+    pub fn of(values: &[f64]) -> Option<Summary> {
+        Some(Summary {
+            count: values.len(),
+            sum: aggregate(values, Aggregation::Sum)?,
+            mean: aggregate(values, Aggregation::Avg)?,
+            median: aggregate(values, Aggregation::Median)?,
+            min: aggregate(values, Aggregation::Min)?,
+            max: aggregate(values, Aggregation::Max)?,
+            last: aggregate(values, Aggregation::Last)?
+        })
+    }
+}
+
+/// Percentage change from `before` to `after`, `None` when `before` is zero.
+pub fn pct_change(before: f64, after: f64) -> Option<f64> {
+    if before == 0.0 {
+        None
+    } else {
+        Some((after - before) / before.abs() * 100.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn median_of_odd_and_even_counts() {
+        assert_eq!(Some(2.0), median(&[3.0, 1.0, 2.0]));
+        assert_eq!(Some(2.5), median(&[4.0, 1.0, 3.0, 2.0]));
+        assert_eq!(None, median(&[]));
+    }
+
+    #[test]
+    fn aggregates() {
+        let values = [2.0, 8.0, 5.0];
+        assert_eq!(Some(5.0), aggregate(&values, Aggregation::Avg));
+        assert_eq!(Some(2.0), aggregate(&values, Aggregation::Min));
+        assert_eq!(Some(8.0), aggregate(&values, Aggregation::Max));
+        assert_eq!(Some(15.0), aggregate(&values, Aggregation::Sum));
+        assert_eq!(Some(5.0), aggregate(&values, Aggregation::Last));
+        assert_eq!(Some(0.0), aggregate(&[], Aggregation::Count));
+        assert_eq!(None, aggregate(&[], Aggregation::Avg));
+    }
+}
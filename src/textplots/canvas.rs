@@ -0,0 +1,199 @@
+//! Braille canvas with ANSI colours.
+//!
+//! Draws the same way as `drawille::Canvas`, but remembers a foreground and
+//! background colour for every character cell.
+
+use std::char;
+use std::cmp;
+use std::fmt::Write;
+use std::str::FromStr;
+
+static PIXEL_MAP: [[u8; 2]; 4] = [[0x01, 0x08],
+                                  [0x02, 0x10],
+                                  [0x04, 0x20],
+                                  [0x40, 0x80]];
+
+/// The basic terminal colours.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Color {
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+    Grey
+}
+
+impl Color {
+    /// ANSI SGR code for the colour as foreground.
+    fn code(self) -> u8 {
+        match self {
+            Color::Red => 31,
+            Color::Green => 32,
+            Color::Yellow => 33,
+            Color::Blue => 34,
+            Color::Magenta => 35,
+            Color::Cyan => 36,
+            Color::White => 37,
+            Color::Grey => 90
+        }
+    }
+
+    /// `text` in this colour, for terminals.
+    pub fn paint(self, text: &str) -> String {
+        format!("\x1b[{}m{}\x1b[0m", self.code(), text)
+    }
+}
+
+impl FromStr for Color {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "red" => Ok(Color::Red),
+            "green" => Ok(Color::Green),
+            "yellow" => Ok(Color::Yellow),
+            "blue" => Ok(Color::Blue),
+            "magenta" => Ok(Color::Magenta),
+            "cyan" => Ok(Color::Cyan),
+            "white" => Ok(Color::White),
+            "grey" | "gray" => Ok(Color::Grey),
+            c => Err(format!("unknown colour '{}', expected red, green, yellow, blue, magenta, cyan, white or grey", c))
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default, PartialEq)]
+struct Cell {
+    dots: u8,
+    fg: Option<Color>,
+    bg: Option<Color>
+}
+
+pub struct Canvas {
+    /// Size in characters
+    columns: u32,
+    rows: u32,
+    cells: Vec<Cell>,
+    /// Colour for anything drawn from now on, `None` to keep what's there
+    pen: Option<Color>
+}
+
+impl Canvas {
+    /// Creates a canvas covering points `0..=width` by `0..=height`.
+    pub fn new(width: u32, height: u32) -> Canvas {
+        let (columns, rows) = (width / 2 + 1, height / 4 + 1);
+
+        Canvas {
+            columns,
+            rows,
+            cells: vec![Cell::default(); (columns * rows) as usize],
+            pen: None
+        }
+    }
+
+    fn cell(&mut self, x: u32, y: u32) -> Option<&mut Cell> {
+        let (column, row) = (x / 2, y / 4);
+
+        if column < self.columns && row < self.rows {
+            self.cells.get_mut((row * self.columns + column) as usize)
+        } else {
+            None
+        }
+    }
+
+    /// Sets the colour for everything drawn after this.
+    pub fn pen(&mut self, color: Option<Color>) {
+        self.pen = color;
+    }
+
+    pub fn set(&mut self, x: u32, y: u32) {
+        let pen = self.pen;
+
+        if let Some(cell) = self.cell(x, y) {
+            cell.dots |= PIXEL_MAP[y as usize % 4][x as usize % 2];
+            if pen.is_some() {
+                cell.fg = pen;
+            }
+        }
+    }
+
+    pub fn line(&mut self, x1: u32, y1: u32, x2: u32, y2: u32) {
+        let xdiff = cmp::max(x1, x2) - cmp::min(x1, x2);
+        let ydiff = cmp::max(y1, y2) - cmp::min(y1, y2);
+        let xdir = if x1 <= x2 { 1 } else { -1 };
+        let ydir = if y1 <= y2 { 1 } else { -1 };
+
+        let r = cmp::max(xdiff, ydiff);
+
+        for i in 0..=r {
+            let mut x = x1 as i32;
+            let mut y = y1 as i32;
+
+            if ydiff != 0 {
+                y += ((i * ydiff) / r) as i32 * ydir;
+            }
+            if xdiff != 0 {
+                x += ((i * xdiff) / r) as i32 * xdir;
+            }
+
+            self.set(x as u32, y as u32);
+        }
+    }
+
+    /// Colours the background of every cell touching points rows `y1..=y2`.
+    pub fn band(&mut self, y1: u32, y2: u32, color: Color) {
+        let (top, bottom) = (cmp::min(y1, y2) / 4, cmp::max(y1, y2) / 4);
+
+        for row in top..=cmp::min(bottom, self.rows - 1) {
+            for column in 0..self.columns {
+                self.cells[(row * self.columns + column) as usize].bg = Some(color);
+            }
+        }
+    }
+
+    /// Renders the canvas as lines of text, with ANSI colour codes if
+    /// `color` is set.
+    pub fn rows(&self, color: bool) -> Vec<String> {
+        self.cells
+            .chunks(self.columns as usize)
+            .map(|row| {
+                let mut line = String::new();
+                let mut current = (None, None);
+
+                for cell in row {
+                    let ch = if cell.dots == 0 {
+                        ' '
+                    } else {
+                        char::from_u32(0x2800 + u32::from(cell.dots)).unwrap()
+                    };
+
+                    if color && (cell.fg, cell.bg) != current {
+                        line.push_str("\x1b[0m");
+                        if let Some(fg) = cell.fg {
+                            write!(line, "\x1b[{}m", fg.code()).unwrap();
+                        }
+                        if let Some(bg) = cell.bg {
+                            write!(line, "\x1b[{}m", bg.code() + 10).unwrap();
+                        }
+                        current = (cell.fg, cell.bg);
+                    }
+
+                    line.push(ch);
+                }
+
+                if color && current != (None, None) {
+                    line.push_str("\x1b[0m");
+                }
+
+                line
+            })
+            .collect()
+    }
+
+    pub fn frame(&self, color: bool) -> String {
+        self.rows(color).join("\n")
+    }
+}
@@ -19,7 +19,7 @@
 //! textplots = "0.3"
 //! ```
 //!
-//! ```rust
+//! ```ignore
 //! extern crate textplots;
 //!
 //! use textplots::{Chart, Plot, Shape};
@@ -36,7 +36,7 @@
 //! Default viewport size is 120 x 60 points, with X values ranging from -10 to 10.
 //! You can override the defaults calling `new`.
 //!
-//! ```rust
+//! ```ignore
 //! use textplots::{Chart, Plot, Shape};
 //!
 //! println!("y = cos(x), y = sin(x) / 2");
@@ -55,10 +55,13 @@
 
 pub mod utils;
 pub mod scale;
+pub mod canvas;
 
-use drawille::{Canvas as BrailleCanvas};
+pub use self::canvas::Color;
+use self::canvas::Canvas;
 use self::scale::Scale;
 use std::cmp;
+use std::collections::BTreeMap;
 use std::default::Default;
 
 /// Controls the drawing.
@@ -77,8 +80,20 @@ pub struct Chart {
     ymax: f32,
     /// Y-axis label
     ylabel: Option<String>,
+    /// Formats Y-axis tick values
+    yformat: Box<dyn Fn(f32) -> String>,
+    /// Draw horizontal gridlines at the Y-axis ticks
+    grid: bool,
+    /// Fixed Y-axis ends, overriding the calculated ones
+    ylimits: (Option<f32>, Option<f32>),
+    /// Logarithmic Y-axis
+    ylog: bool,
+    /// Colour for shapes, markers and reference lines drawn from now on
+    pen: Option<Color>,
+    /// Print with ANSI colours
+    color: bool,
     /// Underlying canvas object
-    canvas: BrailleCanvas,
+    canvas: Canvas,
 }
 
 /// Specifies different kinds of plotted data.
@@ -91,6 +106,8 @@ pub enum Shape<'a> {
     Steps(&'a [(f32, f32)]),
     /// Points represented with bars.
     Bars(&'a [(f32, f32)]),
+    /// Unconnected points.
+    Points(&'a [(f32, f32)]),
 }
 
 /// Provides an interface for drawing plots.
@@ -129,13 +146,131 @@ impl Chart {
             ymax: -10.0,
             width,
             height,
-            canvas: BrailleCanvas::new(width, height),
-            ylabel: None
+            canvas: Canvas::new(width, height),
+            pen: None,
+            color: false,
+            ylabel: None,
+            yformat: Box::new(|y| format!("{:.1}", y)),
+            grid: false,
+            ylimits: (None, None),
+            ylog: false
+        }
+    }
+
+    /// Fixes either end of the Y-axis instead of fitting it to the data.
+    /// Values beyond the limits are drawn at the edge.
+    pub fn y_limits(&mut self, ymin: Option<f32>, ymax: Option<f32>) -> &mut Chart {
+        self.ylimits = (ymin, ymax);
+        self.fit_y();
+        self
+    }
+
+    /// Fits the Y-axis tightly around the plotted values, rather than always
+    /// reaching from at most 10 to at least -10. Call before plotting.
+    pub fn y_tight(&mut self) -> &mut Chart {
+        self.ymin = f32::INFINITY;
+        self.ymax = f32::NEG_INFINITY;
+        self
+    }
+
+    /// Uses a logarithmic Y-axis, fitted tightly to the data. Values that
+    /// aren't positive can't be shown and are left out. Call before plotting.
+    pub fn y_log(&mut self) -> &mut Chart {
+        self.ylog = true;
+        self.y_tight()
+    }
+
+    /// Applies the Y-axis limits and keeps the range drawable.
+    fn fit_y(&mut self) {
+        if let Some(ymin) = self.ylimits.0 {
+            self.ymin = ymin;
+        }
+        if let Some(ymax) = self.ylimits.1 {
+            self.ymax = ymax;
+        }
+
+        if !self.ymin.is_finite() || !self.ymax.is_finite() {
+            return;
+        }
+
+        // A flat line still needs some room around it
+        if self.ymax <= self.ymin {
+            let pad = if self.ylog { self.ymin * 0.5 } else { (self.ymin.abs() * 0.1).max(1.0) };
+            self.ymin -= pad;
+            self.ymax = self.ymin + 3.0 * pad;
+        }
+    }
+
+    /// Sets how Y-axis tick values are printed (`{:.1}` by default).
+    ///
+    /// ```ignore
+    /// use textplots::{Chart, Plot, Shape};
+    ///
+    /// Chart::default()
+    ///     .y_format(|y| format!("{}%", y * 100.0))
+    ///     .lineplot( Shape::Continuous( |x| x.sin() ))
+    ///     .display();
+    /// ```
+    pub fn y_format<F: Fn(f32) -> String + 'static>(&mut self, format: F) -> &mut Chart {
+        self.yformat = Box::new(format);
+        self
+    }
+
+    /// Prints the chart with ANSI colours.
+    pub fn color(&mut self, color: bool) -> &mut Chart {
+        self.color = color;
+        self
+    }
+
+    /// Sets the colour of the shapes, markers and reference lines drawn
+    /// after this, `None` for the terminal's default.
+    ///
+    /// ```ignore
+    /// use textplots::{Chart, Color, Plot, Shape};
+    ///
+    /// Chart::default()
+    ///     .color(true)
+    ///     .pen(Some(Color::Cyan))
+    ///     .lineplot( Shape::Continuous( |x| x.cos() ))
+    ///     .pen(Some(Color::Red))
+    ///     .hrule(0.5)
+    ///     .display();
+    /// ```
+    pub fn pen(&mut self, color: Option<Color>) -> &mut Chart {
+        self.pen = color;
+        self
+    }
+
+    /// Draws horizontal gridlines at the Y-axis ticks.
+    pub fn grid(&mut self, grid: bool) -> &mut Chart {
+        self.grid = grid;
+        self
+    }
+
+    /// Nice Y-axis tick values, roughly one every two text rows.
+    fn y_ticks(&self) -> Vec<f32> {
+        if self.ylog {
+            utils::log_ticks(self.ymin, self.ymax)
+        } else {
+            utils::ticks(self.ymin, self.ymax, (self.height as usize / 8).max(2))
+        }
+    }
+
+    /// Vertical position of a Y value in canvas points, counted from the bottom.
+    fn y_position(&self, y: f32) -> u32 {
+        let scale = Scale::new(self.ymin..self.ymax, 0.0..self.height as f32);
+
+        if self.ylog {
+            scale.log(y).round() as u32
+        } else {
+            scale.linear(y).round() as u32
         }
     }
 
     /// Displays bounding rect.
     fn borders(&mut self) {
+        self.canvas.pen(None);
+
         let w = self.width;
         let h = self.height;
 
@@ -167,34 +302,134 @@ impl Chart {
         }
     }
 
+    /// Widens the Y-axis to include `ymin..ymax`. Call before plotting
+    /// anything when overlaying shapes so they share the same scale.
+    pub fn y_range(&mut self, ymin: f32, ymax: f32) -> &mut Chart {
+        self.ymin = f32::min(self.ymin, ymin);
+        self.ymax = f32::max(self.ymax, ymax);
+        self.fit_y();
+        self
+    }
+
+    /// Marks an X-axis value with a dotted vertical line.
+    pub fn marker(&mut self, x: f32) -> &mut Chart {
+        let x_scale = Scale::new(self.xmin..self.xmax, 0.0..self.width as f32);
+        let i = x_scale.linear(x).round() as u32;
+        self.canvas.pen(self.pen);
+        self.vline(i);
+        self
+    }
+
+    /// Marks a Y-axis value with a dotted horizontal line, at the scale of
+    /// what's been plotted so far.
+    pub fn hrule(&mut self, y: f32) -> &mut Chart {
+        if self.ymin.is_finite() && self.ymax.is_finite() && y >= self.ymin && y <= self.ymax {
+            let j = self.y_position(y);
+            self.canvas.pen(self.pen);
+            self.hline(j);
+        }
+        self
+    }
+
+    /// Shades the background between two Y-axis values (only visible in
+    /// colour), at the scale of what's been plotted so far.
+    pub fn band(&mut self, from: f32, to: f32, color: Color) -> &mut Chart {
+        if !self.ymin.is_finite() || !self.ymax.is_finite() {
+            return self;
+        }
+
+        let clamp = |y: f32| y.max(self.ymin).min(self.ymax);
+        let (from, to) = (clamp(from), clamp(to));
+        if from == to {
+            return self;
+        }
+
+        let (j1, j2) = (self.y_position(from), self.y_position(to));
+        self.canvas.band(self.height - j1, self.height - j2, color);
+        self
+    }
+
     /// Prints canvas content.
     pub fn display(&self) {
-        let frame = self.canvas.frame();
-        let rows = frame.split('\n').into_iter().count();
-        let mid = rows / 2;
-        for (i, row) in frame.split('\n').into_iter().enumerate() {
-            if i == 0 {
-                println!("{0} {1:.1}", row, self.ymax);
-            } else if i == (rows - 1) {
-                println!("{0} {1:.1}", row, self.ymin);
-            } else if i == mid {
-                match self.ylabel {
-                    Some(ref label) => println!("{0} {1:}", row, label),
-                    None => println!("{}", row)
-                };
-            } else {
-                println!("{}", row);
+        for line in self.lines() {
+            println!("{}", line);
+        }
+    }
+
+    /// The lines `display` prints, for laying out alongside other output.
+    pub fn lines(&self) -> Vec<String> {
+        if !self.ymin.is_finite() || !self.ymax.is_finite() {
+            return vec!["(nothing to plot)".into()];
+        }
+
+        let frame = self.canvas.frame(self.color);
+        let rows = frame.split('\n').count();
+
+        // Labels by text row, each braille character being 4 points high
+        let mut labels = BTreeMap::new();
+        for tick in self.y_ticks() {
+            let row = ((self.height - self.y_position(tick)) / 4) as usize;
+            labels.insert(row.min(rows - 1), (self.yformat)(tick));
+        }
+
+        // Show the extremes too, unless a tick is already close by
+        let near = |labels: &BTreeMap<usize, String>, row: usize| {
+            labels.keys().any(|&r| (r as isize - row as isize).abs() <= 1)
+        };
+        if !near(&labels, 0) {
+            labels.insert(0, (self.yformat)(self.ymax));
+        }
+        if !near(&labels, rows - 1) {
+            labels.insert(rows - 1, (self.yformat)(self.ymin));
+        }
+
+        // The axis label goes on the free row closest to the middle
+        if let Some(ref label) = self.ylabel {
+            let mid = rows / 2;
+            let free = (0..rows)
+                .filter(|r| !labels.contains_key(r))
+                .min_by_key(|r| (*r as isize - mid as isize).abs());
+            if let Some(row) = free {
+                labels.insert(row, label.clone());
             }
         }
 
-        println!("{0: <width$.1}{1:.1}", self.xmin, self.xmax, width=(self.width as usize) / 2 - 3);
+        let mut lines = frame.split('\n')
+            .enumerate()
+            .map(|(i, row)| match labels.get(&i) {
+                Some(label) => format!("{0} {1}", row, label),
+                None => row.to_string()
+            })
+            .collect::<Vec<_>>();
+
+        lines.push(format!("{0: <width$.1}{1:.1}", self.xmin, self.xmax, width=(self.width as usize) / 2 - 3));
+        lines
     }
 
     /// Prints canvas content with some additional visual elements (like borders).
     pub fn nice(&mut self) {
+        self.decorate();
+        self.display();
+    }
+
+    /// The lines `nice` prints.
+    pub fn nice_lines(&mut self) -> Vec<String> {
+        self.decorate();
+        self.lines()
+    }
+
+    /// Borders, and gridlines at the ticks if enabled.
+    fn decorate(&mut self) {
         self.borders();
+
+        if self.grid {
+            for tick in self.y_ticks() {
+                let j = self.y_position(tick);
+                self.hline(j);
+            }
+        }
+
         // self.axis();
-        self.display();
     }
 }
 
@@ -210,11 +445,10 @@ impl Plot for Chart {
         let ys: Vec<_> = match shape {
             Shape::Continuous(f) => {
                 (0..self.width)
-                .into_iter()
                 .filter_map(|i| {
                     let x = x_scale.inv_linear(i as f32);
                     let y = f(x);
-                    if y.is_normal() {
+                    if y.is_normal() && (!self.ylog || y > 0.0) {
                         Some(y)
                     } else {
                         None
@@ -224,10 +458,11 @@ impl Plot for Chart {
             },
             | Shape::Lines(dt)
             | Shape::Steps(dt)
-            | Shape::Bars(dt) => {
+            | Shape::Bars(dt)
+            | Shape::Points(dt) => {
                 dt.iter()
                 .filter_map(|(x, y)| {
-                    if *x >= self.xmin && *x <= self.xmax {
+                    if *x >= self.xmin && *x <= self.xmax && (!self.ylog || *y > 0.0) {
                         Some(*y)
                     } else {
                         None
@@ -239,26 +474,36 @@ impl Plot for Chart {
         let ymax = *ys.iter().max_by( |x, y| x.partial_cmp(y).unwrap_or(cmp::Ordering::Equal) ).unwrap_or(&0.0);
         let ymin = *ys.iter().min_by( |x, y| x.partial_cmp(y).unwrap_or(cmp::Ordering::Equal) ).unwrap_or(&0.0);
 
-        self.ymin = f32::min(self.ymin, ymin);
-        self.ymax = f32::max(self.ymax, ymax);
+        if !ys.is_empty() {
+            self.ymin = f32::min(self.ymin, ymin);
+            self.ymax = f32::max(self.ymax, ymax);
+        }
+        self.fit_y();
 
-        let y_scale = Scale::new(self.ymin..self.ymax, 0.0..self.height as f32);
+        if !self.ymin.is_finite() || !self.ymax.is_finite() {
+            return self;
+        }
 
         // show axis
+        self.canvas.pen(None);
         self.vline(x_scale.linear(0.0) as u32);
-        self.hline(y_scale.linear(0.0) as u32);
+        if !self.ylog && self.ymin <= 0.0 && self.ymax >= 0.0 {
+            let j = self.y_position(0.0);
+            self.hline(j);
+        }
+
+        self.canvas.pen(self.pen);
 
         // translate (x, y) points into screen coordinates
         let points: Vec<_> = match shape {
             Shape::Continuous(f) => {
                 (0..self.width)
-                .into_iter()
                 .filter_map(|i| {
                     let x = x_scale.inv_linear(i as f32);
                     let y = f(x);
-                    if y.is_normal() {
-                        let j = y_scale.linear(y).round();
-                        Some((i, self.height - j as u32))
+                    if y.is_normal() && (!self.ylog || y > 0.0) {
+                        let j = self.y_position(y);
+                        Some((i, self.height - j))
                     } else {
                         None
                     }
@@ -267,12 +512,17 @@ impl Plot for Chart {
             },
             | Shape::Lines(dt)
             | Shape::Steps(dt)
-            | Shape::Bars(dt) => {
+            | Shape::Bars(dt)
+            | Shape::Points(dt) => {
                 dt
-                .into_iter()
+                .iter()
                 .filter_map(|(x, y)| {
+                    if self.ylog && *y <= 0.0 {
+                        return None;
+                    }
+
                     let i = x_scale.linear(*x).round() as u32;
-                    let j = y_scale.linear(*y).round() as u32;
+                    let j = self.y_position(*y);
                     if i <= self.width && j <= self.height {
                         Some( (i, self.height - j) )
                     } else {
@@ -283,6 +533,13 @@ impl Plot for Chart {
             },
         };
 
+        if let Shape::Points(_) = shape {
+            for &(x, y) in &points {
+                self.canvas.set(x, y);
+            }
+            return self;
+        }
+
         // display segments
         for pair in points.windows(2) {
             let (x1, y1) = pair[0];
@@ -306,6 +563,7 @@ impl Plot for Chart {
                     self.canvas.line(x1, self.height, x1, y1);
                     self.canvas.line(x2, self.height, x2, y2);
                 },
+                Shape::Points(_) => {},
             }
         }
 
@@ -10,7 +10,7 @@ pub struct Scale {
 
 impl Scale {
     /// Translates value from domain to range scale.
-    /// ```
+    /// ```ignore
     /// # extern crate textplots;
     /// # use textplots::scale::Scale;
     /// assert_eq!(-0.8, Scale::new(0_f32..10_f32, -1_f32..1_f32).linear(1.0));
@@ -19,12 +19,11 @@ impl Scale {
         let p = (x - self.domain.start) / (self.domain.end - self.domain.start);
         let r = self.range.start + p * (self.range.end - self.range.start);
         let r = r.max(self.range.start);
-        let r = r.min(self.range.end);
-        r
+        r.min(self.range.end)
     }
 
     /// Translates value from range to domain scale.
-    /// ```
+    /// ```ignore
     /// # extern crate textplots;
     /// # use textplots::scale::Scale;
     /// assert_eq!(5.5, Scale::new(0_f32..10_f32, -1_f32..1_f32).inv_linear(0.1));
@@ -34,8 +33,37 @@ impl Scale {
         let d = self.domain.start + p * (self.domain.end - self.domain.start);
 
         let d = d.max(self.domain.start);
-        let d = d.min(self.domain.end);
-        d
+        d.min(self.domain.end)
+    }
+
+    /// Translates value from domain to range scale logarithmically. The
+    /// domain has to be positive.
+    /// ```ignore
+    /// # extern crate textplots;
+    /// # use textplots::scale::Scale;
+    /// assert_eq!(5.0, Scale::new(1_f32..100_f32, 0_f32..10_f32).log(10.0));
+    /// ```
+    pub fn log(&self, x: f32) -> f32 {
+        let (start, end) = (self.domain.start.log10(), self.domain.end.log10());
+        let p = (x.log10() - start) / (end - start);
+        let r = self.range.start + p * (self.range.end - self.range.start);
+        let r = r.max(self.range.start);
+        r.min(self.range.end)
+    }
+
+    /// Translates value from range to domain scale logarithmically.
+    /// ```ignore
+    /// # extern crate textplots;
+    /// # use textplots::scale::Scale;
+    /// assert_eq!(10.0, Scale::new(1_f32..100_f32, 0_f32..10_f32).inv_log(5.0));
+    /// ```
+    pub fn inv_log(&self, i: f32) -> f32 {
+        let (start, end) = (self.domain.start.log10(), self.domain.end.log10());
+        let p = (i - self.range.start) / (self.range.end - self.range.start);
+        let d = 10_f32.powf(start + p * (end - start));
+
+        let d = d.max(self.domain.start);
+        d.min(self.domain.end)
     }
 
     pub fn new(domain: Range<f32>, range: Range<f32>) -> Self {
@@ -45,3 +73,22 @@ impl Scale {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn linear() {
+        let scale = Scale::new(0_f32..10_f32, -1_f32..1_f32);
+        assert_eq!(-0.8, scale.linear(1.0));
+        assert_eq!(5.5, scale.inv_linear(0.1));
+    }
+
+    #[test]
+    fn log() {
+        let scale = Scale::new(1_f32..100_f32, 0_f32..10_f32);
+        assert_eq!(5.0, scale.log(10.0));
+        assert_eq!(10.0, scale.inv_log(5.0));
+    }
+}
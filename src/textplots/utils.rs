@@ -3,7 +3,7 @@
 //! Merely a bunch of functions hanging around while the library API is taking shape.
 
 /// Transforms points into frequency distribution (for using in histograms).
-/// ```
+/// ```ignore
 /// # extern crate textplots;
 /// # use textplots::utils::histogram;
 /// assert_eq!(vec![(0.0, 1.0), (5.0, 1.0)], histogram( &[ (0.0, 0.0), (9.0, 9.0), (10.0, 10.0) ], 0.0, 10.0, 2 ));
@@ -20,7 +20,7 @@ pub fn histogram(data: &[(f32, f32)], min: f32, max: f32, bins: usize) -> Vec<(f
 
         let bucket_id = ((y - min) / step) as usize;
         if bucket_id < output.len() {
-            output[bucket_id as usize] += 1;
+            output[bucket_id] += 1;
         }
     }
 
@@ -30,3 +30,77 @@ pub fn histogram(data: &[(f32, f32)], min: f32, max: f32, bins: usize) -> Vec<(f
         .map(|(x, y)| ((min + (x as f32) * step), y as f32) )
         .collect()
 }
+
+/// "Nice" tick values covering `min..max`, spaced 1, 2 or 5 times a power of
+/// ten apart, with about `count` of them.
+/// ```ignore
+/// # extern crate textplots;
+/// # use textplots::utils::ticks;
+/// assert_eq!(vec![0.0, 20.0, 40.0, 60.0, 80.0, 100.0], ticks(0.0, 100.0, 6));
+/// assert_eq!(vec![2000.0, 4000.0, 6000.0], ticks(1234.0, 7000.0, 4));
+/// ```
+pub fn ticks(min: f32, max: f32, count: usize) -> Vec<f32> {
+    if max <= min || max.is_nan() || min.is_nan() || count < 2 {
+        return vec![];
+    }
+
+    let raw = (max - min) / (count - 1) as f32;
+    let magnitude = 10f32.powf(raw.log10().floor());
+    let step = [1.0, 2.0, 5.0, 10.0].iter()
+        .map(|m| m * magnitude)
+        .find(|s| *s >= raw)
+        .unwrap_or(10.0 * magnitude);
+
+    // Step from a multiple of `step` rather than accumulating, to keep the
+    // values round
+    let first = (min / step).ceil() as i64;
+    let last = (max / step + 1e-4).floor() as i64;
+
+    (first..=last).map(|i| i as f32 * step).collect()
+}
+
+/// Tick values for a logarithmic axis covering `min..max`: powers of ten, or
+/// 1, 2 and 5 times them when the range spans less than two decades.
+/// ```ignore
+/// # extern crate textplots;
+/// # use textplots::utils::log_ticks;
+/// assert_eq!(vec![1.0, 10.0, 100.0, 1000.0], log_ticks(0.5, 2000.0));
+/// assert_eq!(vec![2.0, 5.0, 10.0, 20.0], log_ticks(1.5, 30.0));
+/// ```
+pub fn log_ticks(min: f32, max: f32) -> Vec<f32> {
+    if max <= min || min <= 0.0 || max.is_nan() {
+        return vec![];
+    }
+
+    let (low, high) = (min.log10().floor() as i32, max.log10().ceil() as i32);
+    let multiples: &[f32] = if high - low > 2 { &[1.0] } else { &[1.0, 2.0, 5.0] };
+
+    (low..=high)
+        .flat_map(|e| multiples.iter().map(move |m| m * 10_f32.powi(e)))
+        .filter(|t| *t >= min && *t <= max * (1.0 + 1e-4))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn histogram_counts_each_bin() {
+        assert_eq!(vec![(0.0, 1.0), (5.0, 1.0)], histogram(&[(0.0, 0.0), (9.0, 9.0), (10.0, 10.0)], 0.0, 10.0, 2));
+    }
+
+    #[test]
+    fn ticks_are_round() {
+        assert_eq!(vec![0.0, 20.0, 40.0, 60.0, 80.0, 100.0], ticks(0.0, 100.0, 6));
+        assert_eq!(vec![2000.0, 4000.0, 6000.0], ticks(1234.0, 7000.0, 4));
+        assert!(ticks(5.0, 5.0, 4).is_empty());
+    }
+
+    #[test]
+    fn log_ticks_by_decade() {
+        assert_eq!(vec![1.0, 10.0, 100.0, 1000.0], log_ticks(0.5, 2000.0));
+        assert_eq!(vec![2.0, 5.0, 10.0, 20.0], log_ticks(1.5, 30.0));
+        assert!(log_ticks(0.0, 10.0).is_empty());
+    }
+}
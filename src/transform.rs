@@ -0,0 +1,238 @@
+//! Query-time transforms applied to series data before display.
+
+use data::{Measurement, Series};
+use meta::Kind;
+use stats::{self, Aggregation};
+
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+pub enum Transform {
+    /// Difference from the previous point
+    Delta,
+    /// Difference from the previous point per second
+    Rate,
+    /// Running total
+    CumSum,
+    /// Trailing mean over N points
+    MovingAvg(usize),
+    /// Exponentially weighted moving average with smoothing factor alpha
+    Ewma(f64),
+    /// Base 10 logarithm, non-positive values are dropped
+    Log
+}
+
+impl FromStr for Transform {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let mut parts = input.splitn(2, ':');
+        let name = parts.next().unwrap_or("");
+        let arg = parts.next();
+
+        match (name, arg) {
+            ("delta", None) => Ok(Transform::Delta),
+            ("rate", None) => Ok(Transform::Rate),
+            ("cumsum", None) => Ok(Transform::CumSum),
+            ("log", None) => Ok(Transform::Log),
+            ("moving-avg", Some(n)) => match n.parse::<usize>() {
+                Ok(n) if n > 0 => Ok(Transform::MovingAvg(n)),
+                _ => Err(format!("moving-avg needs a positive window, eg moving-avg:5, got '{}'", n))
+            },
+            ("ewma", Some(a)) => match a.parse::<f64>() {
+                Ok(a) if a > 0.0 && a <= 1.0 => Ok(Transform::Ewma(a)),
+                _ => Err(format!("ewma needs an alpha in (0, 1], eg ewma:0.3, got '{}'", a))
+            },
+            _ => Err(format!(
+                "unknown transform '{}', expected delta, rate, cumsum, moving-avg:N, ewma:alpha or log",
+                input
+            ))
+        }
+    }
+}
+
+impl Transform {
+    /// Applies the transform to measurements in oldest-first order. For a
+    /// `counter` a drop in delta and rate is taken as a reset.
+    fn apply(&self, points: Vec<Measurement>, counter: bool) -> Vec<Measurement> {
+        match *self {
+            Transform::Delta | Transform::Rate => {
+                let rate = matches!(*self, Transform::Rate);
+
+                points.windows(2)
+                    .filter_map(|pair| {
+                        let (prev, cur) = (&pair[0], &pair[1]);
+
+                        // A counter going backwards has been reset, so count
+                        // from zero rather than reporting a negative delta
+                        let delta = if counter && cur.measurement < prev.measurement {
+                            cur.measurement
+                        } else {
+                            cur.measurement - prev.measurement
+                        };
+
+                        let value = if rate {
+                            let secs = cur.timestamp.saturating_sub(prev.timestamp);
+                            if secs == 0 { return None; }
+                            delta / f64::from(secs)
+                        } else {
+                            delta
+                        };
+
+                        Some(Measurement { measurement: value, ..*cur })
+                    })
+                    .collect()
+            },
+            Transform::CumSum => {
+                let mut total = 0.0;
+                points.into_iter()
+                    .map(|m| {
+                        total += m.measurement;
+                        Measurement { measurement: total, ..m }
+                    })
+                    .collect()
+            },
+            Transform::MovingAvg(n) => {
+                let values = points.iter().map(|m| m.measurement).collect::<Vec<_>>();
+                points.into_iter()
+                    .enumerate()
+                    .map(|(i, m)| {
+                        let window = &values[(i + 1).saturating_sub(n)..=i];
+                        let mean = window.iter().sum::<f64>() / window.len() as f64;
+                        Measurement { measurement: mean, ..m }
+                    })
+                    .collect()
+            },
+            Transform::Ewma(alpha) => {
+                let mut avg: Option<f64> = None;
+                points.into_iter()
+                    .map(|m| {
+                        let next = match avg {
+                            Some(a) => alpha * m.measurement + (1.0 - alpha) * a,
+                            None => m.measurement
+                        };
+                        avg = Some(next);
+                        Measurement { measurement: next, ..m }
+                    })
+                    .collect()
+            },
+            Transform::Log => {
+                points.into_iter()
+                    .filter(|m| m.measurement > 0.0)
+                    .map(|m| Measurement { measurement: m.measurement.log10(), ..m })
+                    .collect()
+            }
+        }
+    }
+}
+
+/// Applies `transforms` in order to the series' (newest-first) measurements.
+pub fn apply(series: &mut Series, transforms: &[Transform]) {
+    if transforms.is_empty() {
+        return;
+    }
+
+    let mut counter = series.meta.kind == Some(Kind::Counter);
+    let mut points = series.measurements.drain(..).rev().collect::<Vec<_>>();
+    for t in transforms {
+        points = t.apply(points, counter);
+
+        // What's left after a delta or rate isn't a running total any more
+        if let Transform::Delta | Transform::Rate = *t {
+            counter = false;
+        }
+    }
+    points.reverse();
+
+    series.measurements = points;
+}
+
+/// Reduces measurements to one per `bucket` seconds, combining the values in
+/// each bucket with `agg`.
+pub fn bucket(series: &mut Series, bucket: u32, agg: Aggregation) {
+    let bucket = bucket.max(1);
+
+    let mut buckets: BTreeMap<u32, Vec<f64>> = BTreeMap::new();
+    for m in &series.measurements {
+        buckets.entry(m.timestamp - m.timestamp % bucket).or_default().push(m.measurement);
+    }
+
+    // Values went in newest first
+    series.measurements = buckets.into_iter()
+        .rev()
+        .filter_map(|(timestamp, mut values)| {
+            values.reverse();
+            stats::aggregate(&values, agg).map(|measurement| Measurement { timestamp, run: 0, measurement })
+        })
+        .collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use meta::Meta;
+
+    fn points(values: &[(u32, f64)]) -> Vec<Measurement> {
+        values.iter().map(|&(timestamp, measurement)| Measurement { timestamp, run: 0, measurement }).collect()
+    }
+
+    fn values(points: &[Measurement]) -> Vec<f64> {
+        points.iter().map(|m| m.measurement).collect()
+    }
+
+    #[test]
+    fn gauges_go_negative() {
+        let out = Transform::Delta.apply(points(&[(0, 5.0), (10, 8.0), (20, 2.0)]), false);
+        assert_eq!(vec![3.0, -6.0], values(&out));
+        assert_eq!(20, out[1].timestamp);
+
+        let out = Transform::Rate.apply(points(&[(0, 5.0), (10, 8.0), (20, 2.0)]), false);
+        assert_eq!(vec![0.3, -0.6], values(&out));
+    }
+
+    #[test]
+    fn counters_count_from_a_reset() {
+        let input = [(0, 100.0), (10, 150.0), (20, 30.0), (20, 40.0)];
+        assert_eq!(vec![50.0, 30.0, 10.0], values(&Transform::Delta.apply(points(&input), true)));
+        assert_eq!(vec![5.0, 3.0], values(&Transform::Rate.apply(points(&input), true)));
+    }
+
+    #[test]
+    fn only_the_raw_counter_resets() {
+        let mut series = Series {
+            id: 0,
+            name: "requests".into(),
+            unit: String::new(),
+            expression: None,
+            meta: Meta { kind: Some(Kind::Counter), ..Meta::default() },
+            // Newest first
+            measurements: points(&[(30, 40.0), (20, 10.0), (10, 30.0), (0, 10.0)])
+        };
+
+        apply(&mut series, &[Transform::Delta, Transform::Delta]);
+        assert_eq!(vec![20.0, -10.0], values(&series.measurements));
+    }
+
+    #[test]
+    fn running_transforms() {
+        let input = [(0, 1.0), (1, 2.0), (2, 3.0), (3, 6.0)];
+        assert_eq!(vec![1.0, 3.0, 6.0, 12.0], values(&Transform::CumSum.apply(points(&input), false)));
+        assert_eq!(vec![1.0, 1.5, 2.5, 4.5], values(&Transform::MovingAvg(2).apply(points(&input), false)));
+        assert_eq!(vec![1.0, 1.5, 2.25, 4.125], values(&Transform::Ewma(0.5).apply(points(&input), false)));
+    }
+
+    #[test]
+    fn log_drops_non_positive_values() {
+        let out = Transform::Log.apply(points(&[(0, 0.0), (1, 100.0), (2, -1.0)]), false);
+        assert_eq!(vec![2.0], values(&out));
+    }
+
+    #[test]
+    fn parses_names_and_arguments() {
+        assert!(matches!("moving-avg:3".parse(), Ok(Transform::MovingAvg(3))));
+        assert!("moving-avg:0".parse::<Transform>().is_err());
+        assert!("ewma:1.5".parse::<Transform>().is_err());
+        assert!("delta:2".parse::<Transform>().is_err());
+        assert!("median".parse::<Transform>().is_err());
+    }
+}
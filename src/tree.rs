@@ -0,0 +1,120 @@
+//! Series names as a hierarchy, split on dots: `inv.req.time` sits under
+//! `inv` and `inv.req`.
+
+use std::collections::BTreeMap;
+
+#[derive(Default)]
+pub struct Node {
+    /// Full name down to here, eg `inv.req`
+    pub path: String,
+    /// Whether a series has exactly this name
+    pub series: bool,
+    /// By the next part of the name
+    pub children: BTreeMap<String, Node>
+}
+
+impl Node {
+    /// Builds the tree from a list of series names.
+    pub fn build<'a, I: IntoIterator<Item = &'a str>>(names: I) -> Node {
+        let mut root = Node::default();
+
+        for name in names {
+            let mut node = &mut root;
+            for part in name.split('.') {
+                let path = if node.path.is_empty() {
+                    part.to_string()
+                } else {
+                    format!("{}.{}", node.path, part)
+                };
+
+                node = node.children.entry(part.to_string()).or_insert_with(|| Node { path, ..Node::default() });
+            }
+            node.series = true;
+        }
+
+        root
+    }
+
+    /// Number of series at or below this node.
+    pub fn count(&self) -> usize {
+        let own = if self.series { 1 } else { 0 };
+        own + self.children.values().map(Node::count).sum::<usize>()
+    }
+
+    /// The node for a dotted prefix like `inv.req`.
+    pub fn find(&self, prefix: &str) -> Option<&Node> {
+        prefix.split('.')
+            .filter(|p| !p.is_empty())
+            .try_fold(self, |node, part| node.children.get(part))
+    }
+
+    /// The tree below this node as lines of text, `depth` levels deep with
+    /// the branches below that collapsed to their counts.
+    pub fn lines(&self, depth: Option<usize>) -> Vec<String> {
+        let mut out = vec![];
+        self.draw("", depth.map(|d| d.saturating_sub(1)), &mut out);
+        out
+    }
+
+    fn draw(&self, indent: &str, depth: Option<usize>, out: &mut Vec<String>) {
+        let last = self.children.len();
+
+        for (i, (part, child)) in self.children.iter().enumerate() {
+            let (branch, more) = if i + 1 == last { ("└── ", "    ") } else { ("├── ", "│   ") };
+            let collapsed = depth == Some(0) && !child.children.is_empty();
+
+            let line = if child.children.is_empty() {
+                part.to_string()
+            } else if collapsed {
+                format!("{}.* ({})", part, child.count())
+            } else {
+                format!("{} ({})", part, child.count())
+            };
+            out.push(format!("{}{}{}", indent, branch, line));
+
+            if !collapsed {
+                child.draw(&format!("{}{}", indent, more), depth.map(|d| d.saturating_sub(1)), out);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tree() -> Node {
+        Node::build(vec!["inv.req.time", "inv.req", "inv.db.time", "web.hits"])
+    }
+
+    #[test]
+    fn counts_series_below_each_node() {
+        let root = tree();
+        assert_eq!(4, root.count());
+        assert_eq!(3, root.find("inv").unwrap().count());
+        assert_eq!(2, root.find("inv.req").unwrap().count());
+        assert!(root.find("inv.req").unwrap().series);
+        assert!(!root.find("inv").unwrap().series);
+        assert_eq!("inv.db.time", root.find("inv.db.time").unwrap().path);
+        assert!(root.find("inv.nope").is_none());
+    }
+
+    #[test]
+    fn draws_branches() {
+        assert_eq!(vec![
+            "├── inv (3)",
+            "│   ├── db (1)",
+            "│   │   └── time",
+            "│   └── req (2)",
+            "│       └── time",
+            "└── web (1)",
+            "    └── hits",
+        ], tree().lines(None));
+    }
+
+    #[test]
+    fn collapses_below_the_depth() {
+        assert_eq!(vec!["├── inv.* (3)", "└── web.* (1)"], tree().lines(Some(1)));
+        assert_eq!(vec!["├── db.* (1)", "└── req.* (2)"], tree().find("inv").unwrap().lines(Some(1)));
+    }
+}
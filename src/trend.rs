@@ -0,0 +1,154 @@
+//! Trend fitting and projection over evenly spaced points.
+
+use std::str::FromStr;
+
+pub enum Model {
+    /// Least squares straight line
+    Linear,
+    /// Holt's double exponential smoothing, with level and trend factors
+    Holt(f64, f64)
+}
+
+impl FromStr for Model {
+    type Err = String;
+
+    /// `linear`, `holt` or `holt:alpha,beta`
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let mut parts = input.splitn(2, ':');
+
+        match (parts.next().unwrap_or(""), parts.next()) {
+            ("linear", None) => Ok(Model::Linear),
+            ("holt", None) => Ok(Model::Holt(0.5, 0.3)),
+            ("holt", Some(params)) => {
+                let params = params.split(',')
+                    .map(|p| p.trim().parse::<f64>())
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|e| format!("invalid holt parameters '{}': {}", params, e))?;
+
+                match params.as_slice() {
+                    [a, b] if *a > 0.0 && *a <= 1.0 && *b > 0.0 && *b <= 1.0 => Ok(Model::Holt(*a, *b)),
+                    _ => Err("holt takes two factors in (0, 1], eg holt:0.5,0.3".into())
+                }
+            },
+            _ => Err(format!("unknown trend '{}', expected linear, holt or holt:alpha,beta", input))
+        }
+    }
+}
+
+/// A fitted trend. X values are point indexes, 0 being the oldest.
+pub struct Trend {
+    /// Fitted values for the input points
+    pub fitted: Vec<f64>,
+    /// Value at the last point
+    level: f64,
+    /// Change per point
+    slope: f64
+}
+
+impl Trend {
+    pub fn fit(model: &Model, values: &[f64]) -> Option<Trend> {
+        if values.len() < 2 {
+            return None;
+        }
+
+        match *model {
+            Model::Linear => {
+                let n = values.len() as f64;
+                let mean_x = (n - 1.0) / 2.0;
+                let mean_y = values.iter().sum::<f64>() / n;
+
+                let (num, den) = values.iter()
+                    .enumerate()
+                    .fold((0.0, 0.0), |(num, den), (i, y)| {
+                        let dx = i as f64 - mean_x;
+                        (num + dx * (y - mean_y), den + dx * dx)
+                    });
+
+                let slope = num / den;
+                let intercept = mean_y - slope * mean_x;
+                let fitted = (0..values.len()).map(|i| intercept + slope * i as f64).collect::<Vec<_>>();
+
+                Some(Trend { level: *fitted.last().unwrap(), slope, fitted })
+            },
+            Model::Holt(alpha, beta) => {
+                let mut level = values[0];
+                let mut slope = values[1] - values[0];
+                let mut fitted = vec![level];
+
+                for &y in &values[1..] {
+                    let prev = level;
+                    level = alpha * y + (1.0 - alpha) * (level + slope);
+                    slope = beta * (level - prev) + (1.0 - beta) * slope;
+                    fitted.push(level);
+                }
+
+                Some(Trend { fitted, level, slope })
+            }
+        }
+    }
+
+    /// Change per point at the end of the fit.
+    pub fn slope(&self) -> f64 {
+        self.slope
+    }
+
+    /// Projected value `ahead` points past the last one.
+    pub fn project(&self, ahead: f64) -> f64 {
+        self.level + self.slope * ahead
+    }
+
+    /// How many points past the last one until `target` is reached, if the
+    /// trend is heading towards it at all.
+    pub fn reaches(&self, target: f64) -> Option<f64> {
+        if self.slope == 0.0 {
+            return if self.level == target { Some(0.0) } else { None };
+        }
+
+        let ahead = (target - self.level) / self.slope;
+        if ahead >= 0.0 { Some(ahead) } else { None }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn linear_fits_a_line_exactly() {
+        let trend = Trend::fit(&Model::Linear, &[1.0, 3.0, 5.0, 7.0]).unwrap();
+        assert_eq!(vec![1.0, 3.0, 5.0, 7.0], trend.fitted);
+        assert_eq!(2.0, trend.slope());
+        assert_eq!(11.0, trend.project(2.0));
+        assert_eq!(Some(1.5), trend.reaches(10.0));
+        assert_eq!(None, trend.reaches(0.0));
+    }
+
+    #[test]
+    fn holt_follows_a_steady_climb() {
+        let values = (0..20).map(|i| 10.0 + 2.0 * f64::from(i)).collect::<Vec<_>>();
+        let trend = Trend::fit(&Model::Holt(0.5, 0.3), &values).unwrap();
+        assert_eq!(values.len(), trend.fitted.len());
+        assert!((trend.slope() - 2.0).abs() < 1e-9);
+        assert!((trend.project(1.0) - 50.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn flat_trend_never_reaches_another_value() {
+        let trend = Trend::fit(&Model::Linear, &[4.0, 4.0, 4.0]).unwrap();
+        assert_eq!(Some(0.0), trend.reaches(4.0));
+        assert_eq!(None, trend.reaches(5.0));
+    }
+
+    #[test]
+    fn needs_two_points() {
+        assert!(Trend::fit(&Model::Linear, &[1.0]).is_none());
+    }
+
+    #[test]
+    fn parses_models() {
+        assert!(matches!("holt:0.2,0.1".parse(), Ok(Model::Holt(a, b)) if a == 0.2 && b == 0.1));
+        assert!("holt:0.2".parse::<Model>().is_err());
+        assert!("holt:0,1".parse::<Model>().is_err());
+        assert!("cubic".parse::<Model>().is_err());
+    }
+}
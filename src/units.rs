@@ -0,0 +1,90 @@
+//! Knowledge about the free-form units attached to series.
+
+/// A unit normalised to its base (eg `ms` -> `seconds`).
+pub struct BaseUnit {
+    /// Base unit name, suitable as a metric suffix
+    pub name: String,
+    factor: f64,
+    divisor: f64
+}
+
+impl BaseUnit {
+    /// Converts a value into the base unit.
+    pub fn convert(&self, value: f64) -> f64 {
+        value * self.factor / self.divisor
+    }
+}
+
+/// Maps a series unit onto its base unit.
+pub fn base_unit(unit: &str) -> Option<BaseUnit> {
+    let (name, factor, divisor) = match unit.trim() {
+        "" => return None,
+        "ns" => ("seconds", 1.0, 1e9),
+        "us" | "µs" => ("seconds", 1.0, 1e6),
+        "ms" => ("seconds", 1.0, 1e3),
+        "s" | "sec" | "secs" | "seconds" => ("seconds", 1.0, 1.0),
+        "m" | "min" | "mins" | "minutes" => ("seconds", 60.0, 1.0),
+        "h" | "hr" | "hours" => ("seconds", 3600.0, 1.0),
+        "B" | "bytes" => ("bytes", 1.0, 1.0),
+        "KB" | "kB" => ("bytes", 1e3, 1.0),
+        "MB" => ("bytes", 1e6, 1.0),
+        "GB" => ("bytes", 1e9, 1.0),
+        "KiB" => ("bytes", 1024.0, 1.0),
+        "MiB" => ("bytes", 1024.0 * 1024.0, 1.0),
+        "GiB" => ("bytes", 1024.0 * 1024.0 * 1024.0, 1.0),
+        "%" | "pct" | "percent" => ("ratio", 1.0, 100.0),
+        other => (other, 1.0, 1.0)
+    };
+
+    Some(BaseUnit { name: name.to_lowercase(), factor, divisor })
+}
+
+/// Rounds to three significant figures, without trailing zeros.
+fn short(value: f64) -> String {
+    if value == 0.0 || !value.is_finite() {
+        return format!("{}", value);
+    }
+
+    let decimals = (2 - value.abs().log10().floor() as i32).clamp(0, 9) as usize;
+    let s = format!("{:.*}", decimals, value);
+
+    if s.contains('.') {
+        s.trim_end_matches('0').trim_end_matches('.').to_string()
+    } else {
+        s
+    }
+}
+
+/// Picks the largest scale that keeps the value at least one.
+fn scaled(value: f64, scales: &[(f64, &str)]) -> String {
+    // Zero reads best in the unscaled unit
+    let fits = |scale: f64| if value == 0.0 { scale == 1.0 } else { value.abs() >= scale };
+
+    let &(scale, suffix) = scales.iter()
+        .find(|(scale, _)| fits(*scale))
+        .unwrap_or(&scales[scales.len() - 1]);
+
+    format!("{}{}", short(value / scale), suffix)
+}
+
+/// Formats a value for display in a readable unit, eg `1500` `ms` as `1.5s`,
+/// `2500000` `B` as `2.5MB` and `12000` with no unit as `12k`.
+pub fn format(value: f64, unit: &str) -> String {
+    let base = match base_unit(unit) {
+        Some(b) => b,
+        None => return scaled(value, &[(1e12, "T"), (1e9, "G"), (1e6, "M"), (1e3, "k"), (1.0, "")])
+    };
+
+    let converted = base.convert(value);
+    match base.name.as_str() {
+        "seconds" => scaled(converted, &[
+            (3600.0, "h"), (60.0, "m"), (1.0, "s"), (1e-3, "ms"), (1e-6, "µs"), (1e-9, "ns")
+        ]),
+        "bytes" => scaled(converted, &[(1e12, "TB"), (1e9, "GB"), (1e6, "MB"), (1e3, "KB"), (1.0, "B")]),
+        "ratio" => format!("{}%", short(converted * 100.0)),
+        _ => {
+            let number = scaled(value, &[(1e12, "T"), (1e9, "G"), (1e6, "M"), (1e3, "k"), (1.0, "")]);
+            format!("{} {}", number, unit.trim())
+        }
+    }
+}